@@ -0,0 +1,99 @@
+//! Generates the Bambu model-code lookup table from
+//! `resources/bambu_models.csv` so adding a newly released printer is a
+//! data-file edit rather than a source change to `wifi_manager.rs`'s
+//! discovery parser. The generated table is included by `bambu_models.rs`.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+fn main() {
+    println!("cargo:rerun-if-changed=resources/bambu_models.csv");
+
+    let csv = fs::read_to_string("resources/bambu_models.csv")
+        .expect("failed to read resources/bambu_models.csv");
+
+    let mut entries = String::new();
+    for (line_num, line) in csv.lines().enumerate().skip(1) {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let mut cols = line.splitn(3, ',');
+        let code = cols
+            .next()
+            .unwrap_or_else(|| panic!("bambu_models.csv:{}: missing code column", line_num + 1))
+            .trim();
+        let series = cols
+            .next()
+            .unwrap_or_else(|| panic!("bambu_models.csv:{}: missing series column", line_num + 1))
+            .trim();
+        let friendly_name = cols
+            .next()
+            .unwrap_or_else(|| panic!("bambu_models.csv:{}: missing friendly_name column", line_num + 1))
+            .trim();
+
+        entries.push_str(&format!(
+            "    ModelInfo {{ code: {code:?}, series: ModelSeries::{series}, friendly_name: {friendly_name:?} }},\n",
+            code = code,
+            series = series,
+            friendly_name = friendly_name,
+        ));
+    }
+
+    let generated = format!(
+        r#"/// A Bambu printer model series, as advertised by its discovery model code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelSeries {{
+    X1,
+    P1,
+    A1,
+    P2,
+    H2,
+    Unknown,
+}}
+
+/// One row of the generated model-code table.
+#[derive(Debug, Clone, Copy)]
+pub struct ModelInfo {{
+    pub code: &'static str,
+    pub series: ModelSeries,
+    pub friendly_name: &'static str,
+}}
+
+/// Generated from `resources/bambu_models.csv` at build time - add a newly
+/// released printer there rather than editing this table.
+pub static BAMBU_MODELS: &[ModelInfo] = &[
+{entries}];
+
+/// Look up a discovery model code's table entry.
+pub fn model_info(code: &str) -> Option<&'static ModelInfo> {{
+    BAMBU_MODELS.iter().find(|m| m.code == code)
+}}
+
+/// The model series for a discovery model code, or `Unknown` for an empty
+/// or unrecognized code.
+pub fn series(code: &str) -> ModelSeries {{
+    model_info(code).map(|m| m.series).unwrap_or(ModelSeries::Unknown)
+}}
+
+/// The friendly display name for a discovery model code, falling back to
+/// "Bambu Printer" for an empty code or the raw code itself for one the
+/// table doesn't recognize yet.
+pub fn friendly_name(code: &str) -> String {{
+    if code.is_empty() {{
+        return "Bambu Printer".to_string();
+    }}
+    match model_info(code) {{
+        Some(m) => m.friendly_name.to_string(),
+        None => code.to_string(),
+    }}
+}}
+"#,
+        entries = entries,
+    );
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    fs::write(Path::new(&out_dir).join("bambu_models.rs"), generated).unwrap();
+}
@@ -0,0 +1,5 @@
+//! Bambu model-code lookup table, generated at build time from
+//! `resources/bambu_models.csv` (see `build.rs`). Add a newly released
+//! printer by editing that file, not this one.
+
+include!(concat!(env!("OUT_DIR"), "/bambu_models.rs"));
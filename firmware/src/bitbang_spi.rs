@@ -0,0 +1,251 @@
+//! Bit-banged SPI, for boards where the hardware SPI peripheral can't be
+//! trusted (the CrowPanel's broken SPI3 GPIO routing being the motivating
+//! case - see the loopback tests that used to live in `main.rs`).
+//!
+//! `BitBangSpi` drives SCK/MOSI by hand and samples MISO directly through
+//! `PinDriver`, implementing `embedded-hal`'s `SpiBus`/`SpiDevice` traits so
+//! it's a drop-in substitute anywhere a hardware `SpiDeviceDriver` is
+//! expected (e.g. `Pn5180Driver<'a, SPI>`). Like the hardware driver as used
+//! today (`SpiDeviceDriver::new(..., None, ...)`), chip-select is left to the
+//! caller: `transaction`/`SpiDevice` here never touches CS, so the PN5180
+//! driver's manual NSS handling and its inter-frame guard delay keep working
+//! unchanged.
+
+use core::hint::spin_loop;
+
+use embedded_hal::spi::{Error, ErrorKind, ErrorType, Operation, Phase, Polarity, SpiBus, SpiDevice};
+use esp_idf_hal::delay::Ets;
+use esp_idf_hal::gpio::{Input, InputPin, Output, OutputPin, PinDriver};
+
+/// Order bits are shifted out/in within a byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitOrder {
+    MsbFirst,
+    LsbFirst,
+}
+
+/// How long to hold each half of the clock period.
+#[derive(Debug, Clone, Copy)]
+pub enum ClockDelay {
+    /// Busy-spin `cycles` times; the shortest, highest-jitter option.
+    SpinLoop(u32),
+    /// Busy-wait `micros` microseconds via `esp_idf_hal::delay::Ets`.
+    Micros(u32),
+}
+
+/// Clock mode and bit order for a `BitBangSpi` transfer.
+#[derive(Debug, Clone, Copy)]
+pub struct BitBangSpiConfig {
+    pub polarity: Polarity,
+    pub phase: Phase,
+    pub bit_order: BitOrder,
+    pub clock_delay: ClockDelay,
+}
+
+impl Default for BitBangSpiConfig {
+    /// SPI Mode 0 (CPOL=0, CPHA=0), MSB-first, matching the PN5180's
+    /// requirements and the original `bitbang_byte!` macro's timing.
+    fn default() -> Self {
+        BitBangSpiConfig {
+            polarity: Polarity::IdleLow,
+            phase: Phase::CaptureOnFirstTransition,
+            bit_order: BitOrder::MsbFirst,
+            clock_delay: ClockDelay::SpinLoop(10),
+        }
+    }
+}
+
+/// The only error a bit-banged bus can produce: driving one of its GPIOs failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitBangSpiError;
+
+impl Error for BitBangSpiError {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+/// A software SPI bus over three manually-driven GPIOs.
+pub struct BitBangSpi<'a, SCK, MOSI, MISO>
+where
+    SCK: OutputPin,
+    MOSI: OutputPin,
+    MISO: InputPin,
+{
+    sck: PinDriver<'a, SCK, Output>,
+    mosi: PinDriver<'a, MOSI, Output>,
+    miso: PinDriver<'a, MISO, Input>,
+    config: BitBangSpiConfig,
+}
+
+impl<'a, SCK, MOSI, MISO> BitBangSpi<'a, SCK, MOSI, MISO>
+where
+    SCK: OutputPin,
+    MOSI: OutputPin,
+    MISO: InputPin,
+{
+    pub fn new(
+        sck: PinDriver<'a, SCK, Output>,
+        mosi: PinDriver<'a, MOSI, Output>,
+        miso: PinDriver<'a, MISO, Input>,
+        config: BitBangSpiConfig,
+    ) -> Self {
+        let mut bus = BitBangSpi { sck, mosi, miso, config };
+        let _ = bus.set_sck(bus.config.polarity == Polarity::IdleHigh);
+        bus
+    }
+
+    fn delay_half_period(&self) {
+        match self.config.clock_delay {
+            ClockDelay::SpinLoop(cycles) => {
+                for _ in 0..cycles {
+                    spin_loop();
+                }
+            }
+            ClockDelay::Micros(us) => Ets::delay_us(us),
+        }
+    }
+
+    fn set_sck(&mut self, high: bool) -> Result<(), BitBangSpiError> {
+        if high {
+            self.sck.set_high()
+        } else {
+            self.sck.set_low()
+        }
+        .map_err(|_| BitBangSpiError)
+    }
+
+    fn set_mosi(&mut self, high: bool) -> Result<(), BitBangSpiError> {
+        if high {
+            self.mosi.set_high()
+        } else {
+            self.mosi.set_low()
+        }
+        .map_err(|_| BitBangSpiError)
+    }
+
+    /// Shift one byte out MOSI while shifting one byte in from MISO, per the
+    /// configured mode/bit order. This is the `bitbang_byte!` macro's Mode-0
+    /// sequence (set MOSI, delay, rising edge + sample, delay, falling edge,
+    /// delay), generalized to all four CPOL/CPHA combinations.
+    fn transfer_byte(&mut self, tx: u8) -> Result<u8, BitBangSpiError> {
+        let idle_high = self.config.polarity == Polarity::IdleHigh;
+        let sample_on_leading_edge = self.config.phase == Phase::CaptureOnFirstTransition;
+        let mut rx = 0u8;
+
+        for i in 0..8 {
+            let bit_idx = match self.config.bit_order {
+                BitOrder::MsbFirst => 7 - i,
+                BitOrder::LsbFirst => i,
+            };
+            let out_bit = (tx >> bit_idx) & 1 == 1;
+
+            if sample_on_leading_edge {
+                self.set_mosi(out_bit)?;
+                self.delay_half_period();
+                self.set_sck(!idle_high)?; // leading edge
+                let bit = self.miso.is_high();
+                self.delay_half_period();
+                self.set_sck(idle_high)?; // trailing edge
+                self.delay_half_period();
+                if bit {
+                    rx |= 1 << bit_idx;
+                }
+            } else {
+                self.set_sck(!idle_high)?; // leading edge: shift next bit out
+                self.set_mosi(out_bit)?;
+                self.delay_half_period();
+                self.set_sck(idle_high)?; // trailing edge: sample
+                let bit = self.miso.is_high();
+                self.delay_half_period();
+                if bit {
+                    rx |= 1 << bit_idx;
+                }
+            }
+        }
+
+        Ok(rx)
+    }
+}
+
+impl<'a, SCK, MOSI, MISO> ErrorType for BitBangSpi<'a, SCK, MOSI, MISO>
+where
+    SCK: OutputPin,
+    MOSI: OutputPin,
+    MISO: InputPin,
+{
+    type Error = BitBangSpiError;
+}
+
+impl<'a, SCK, MOSI, MISO> SpiBus<u8> for BitBangSpi<'a, SCK, MOSI, MISO>
+where
+    SCK: OutputPin,
+    MOSI: OutputPin,
+    MISO: InputPin,
+{
+    fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        for w in words.iter_mut() {
+            *w = self.transfer_byte(0xFF)?;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        for &w in words {
+            self.transfer_byte(w)?;
+        }
+        Ok(())
+    }
+
+    fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+        let len = read.len().max(write.len());
+        for i in 0..len {
+            let tx = write.get(i).copied().unwrap_or(0xFF);
+            let rx = self.transfer_byte(tx)?;
+            if let Some(slot) = read.get_mut(i) {
+                *slot = rx;
+            }
+        }
+        Ok(())
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        for w in words.iter_mut() {
+            *w = self.transfer_byte(*w)?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl<'a, SCK, MOSI, MISO> SpiDevice<u8> for BitBangSpi<'a, SCK, MOSI, MISO>
+where
+    SCK: OutputPin,
+    MOSI: OutputPin,
+    MISO: InputPin,
+{
+    /// CS is manual (held by the caller, e.g. `Pn5180Driver::nss`), so this
+    /// never asserts/deasserts anything of its own - it just runs the
+    /// requested operations back-to-back, matching how the hardware
+    /// `SpiDeviceDriver` behaves today with `cs: None`.
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+        for op in operations {
+            match op {
+                Operation::Read(buf) => self.read(buf)?,
+                Operation::Write(buf) => self.write(buf)?,
+                Operation::Transfer(read, write) => self.transfer(read, write)?,
+                Operation::TransferInPlace(buf) => self.transfer_in_place(buf)?,
+                Operation::DelayNs(ns) => {
+                    let micros = ns.div_ceil(1000);
+                    if micros > 0 {
+                        Ets::delay_us(micros);
+                    }
+                }
+            }
+        }
+        self.flush()
+    }
+}
@@ -0,0 +1,307 @@
+//! Runtime-configurable pin assignments for the NFC SPI bus and scale I2C
+//! bus, persisted to NVS.
+//!
+//! The CrowPanel pin-conflict saga (GPIO15 touch conflict, GPIO4/6 shorting,
+//! "try a different GPIO for SCK") has meant every fix so far was a
+//! `main.rs` edit and a reflash. This module loads a `BoardConfig` from NVS
+//! at boot, falling back to the compiled-in defaults `main.rs` has always
+//! used when NVS has nothing saved, and lets a new assignment be validated
+//! (via `pin_caps`) and written live - `board_config_apply` persists it and
+//! reboots so it takes effect without a toolchain.
+
+use std::ffi::c_int;
+use std::sync::Mutex;
+
+use esp_idf_svc::nvs::{EspDefaultNvsPartition, EspNvs};
+use log::{info, warn};
+
+use crate::pin_caps::{self, SpiRole};
+use crate::spi_routing::SpiHost;
+
+const NVS_NAMESPACE: &str = "board_cfg";
+const NVS_KEY_NFC_SPI_HOST: &str = "nfc_spi_host";
+const NVS_KEY_NFC_SCK: &str = "nfc_sck";
+const NVS_KEY_NFC_MOSI: &str = "nfc_mosi";
+const NVS_KEY_NFC_MISO: &str = "nfc_miso";
+const NVS_KEY_NFC_NSS: &str = "nfc_nss";
+const NVS_KEY_NFC_BAUD: &str = "nfc_baud";
+const NVS_KEY_NFC_READ_BAUD: &str = "nfc_rd_baud";
+const NVS_KEY_NFC_BITBANG_DELAY_US: &str = "nfc_bb_us";
+const NVS_KEY_SCALE_SDA: &str = "scale_sda";
+const NVS_KEY_SCALE_SCL: &str = "scale_scl";
+const NVS_KEY_SCALE_BAUD: &str = "scale_baud";
+
+/// GPIO assignment and bus speed for the NFC SPI bus and the scale I2C bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoardConfig {
+    /// Which GP-SPI peripheral drives the NFC bus - `SpiHost::Spi3` by
+    /// default, `SpiHost::Spi2` when a user remaps around a SPI3 pin
+    /// conflict. Stored as a `u8` (0=Spi2, 1=Spi3) since that's what NVS and
+    /// the FFI struct can hold directly; use `nfc_spi_host()` rather than
+    /// matching on the raw value.
+    pub nfc_spi_host: u8,
+    pub nfc_sck: u8,
+    pub nfc_mosi: u8,
+    pub nfc_miso: u8,
+    pub nfc_nss: u8,
+    /// Clock used for the command (write) phase of a PN5180 transaction.
+    pub nfc_baud_hz: u32,
+    /// Clock used for the response (read) phase. MISO's round-trip delay,
+    /// not MOSI's edge rate, is usually what limits safe read speed on a
+    /// long or capacitively-loaded bus, so this can be set lower than
+    /// `nfc_baud_hz` without giving up write throughput. Only the hardware
+    /// SPI3 path (`spi3_dual_clock`) can act on this independently of the
+    /// write clock; the bit-banged fallback runs both phases at one speed.
+    pub nfc_read_baud_hz: u32,
+    /// Half-period delay for the bit-banged SPI fallback (`bitbang_spi`),
+    /// used when the hardware SPI3 peripheral can't be brought up. Only
+    /// matters on that fallback path; the hardware path ignores it.
+    pub nfc_bitbang_delay_us: u32,
+    pub scale_sda: u8,
+    pub scale_scl: u8,
+    pub scale_baud_hz: u32,
+}
+
+impl Default for BoardConfig {
+    /// The wiring `main()` has always used: SPI3 on J9 (SCK=5, MOSI=6,
+    /// MISO=4, NSS=8 on J11), scale I2C1 on the UART1-OUT header (SDA=19,
+    /// SCL=20).
+    fn default() -> Self {
+        BoardConfig {
+            nfc_spi_host: spi_host_to_u8(SpiHost::Spi3),
+            nfc_sck: 5,
+            nfc_mosi: 6,
+            nfc_miso: 4,
+            nfc_nss: 8,
+            nfc_baud_hz: 1_000_000,
+            nfc_read_baud_hz: 500_000,
+            nfc_bitbang_delay_us: 5,
+            scale_sda: 19,
+            scale_scl: 20,
+            scale_baud_hz: 100_000,
+        }
+    }
+}
+
+impl BoardConfig {
+    /// Check every pin against `pin_caps` for the role it'll be asked to
+    /// play, so a bad remap is rejected before it's saved rather than
+    /// discovered by a bricked boot.
+    fn validate(&self) -> Result<(), pin_caps::PinCapsError> {
+        u8_to_spi_host(self.nfc_spi_host).ok_or(pin_caps::PinCapsError::NoSuchPin(self.nfc_spi_host))?;
+        pin_caps::validate_spi_pins(self.nfc_sck, self.nfc_mosi, self.nfc_miso, None, SpiRole::Master)?;
+        pin_caps::validate_gpio_output(self.nfc_nss)?;
+        pin_caps::validate_i2c_pins(self.scale_sda, self.scale_scl)?;
+        Ok(())
+    }
+
+    /// Which GP-SPI peripheral the NFC bus should use. Falls back to
+    /// `SpiHost::Spi3` (the compiled-in default) if `nfc_spi_host` somehow
+    /// holds a value other than 0 or 1 - `validate()` should have already
+    /// rejected that, but this keeps the accessor total.
+    pub fn nfc_spi_host(&self) -> SpiHost {
+        u8_to_spi_host(self.nfc_spi_host).unwrap_or(SpiHost::Spi3)
+    }
+}
+
+fn spi_host_to_u8(host: SpiHost) -> u8 {
+    match host {
+        SpiHost::Spi2 => 0,
+        SpiHost::Spi3 => 1,
+    }
+}
+
+fn u8_to_spi_host(value: u8) -> Option<SpiHost> {
+    match value {
+        0 => Some(SpiHost::Spi2),
+        1 => Some(SpiHost::Spi3),
+        _ => None,
+    }
+}
+
+/// Currently active config, set once by `init_board_config` at startup.
+static ACTIVE_CONFIG: Mutex<Option<BoardConfig>> = Mutex::new(None);
+/// Held onto so `board_config_apply` can write a new config without `main()`
+/// threading the NVS partition back in.
+static NVS_PARTITION: Mutex<Option<EspDefaultNvsPartition>> = Mutex::new(None);
+
+/// Load the board config from NVS (falling back to `BoardConfig::default()`
+/// if NVS is unavailable, empty, or holds a config that fails pin
+/// validation), cache it and the NVS partition for `board_config_apply`,
+/// and return it. Call once from `main()`, before building the SPI/I2C
+/// buses it describes.
+pub fn init_board_config(nvs: Option<EspDefaultNvsPartition>) -> BoardConfig {
+    let config = load(nvs.as_ref());
+    *NVS_PARTITION.lock().unwrap() = nvs;
+    *ACTIVE_CONFIG.lock().unwrap() = Some(config);
+    config
+}
+
+fn load(nvs: Option<&EspDefaultNvsPartition>) -> BoardConfig {
+    let default = BoardConfig::default();
+
+    let Some(nvs_partition) = nvs else {
+        return default;
+    };
+    let Ok(nvs) = EspNvs::new(nvs_partition.clone(), NVS_NAMESPACE, true) else {
+        warn!("Failed to open {} NVS namespace, using compiled defaults", NVS_NAMESPACE);
+        return default;
+    };
+
+    let config = BoardConfig {
+        nfc_spi_host: nvs.get_u8(NVS_KEY_NFC_SPI_HOST).ok().flatten().unwrap_or(default.nfc_spi_host),
+        nfc_sck: nvs.get_u8(NVS_KEY_NFC_SCK).ok().flatten().unwrap_or(default.nfc_sck),
+        nfc_mosi: nvs.get_u8(NVS_KEY_NFC_MOSI).ok().flatten().unwrap_or(default.nfc_mosi),
+        nfc_miso: nvs.get_u8(NVS_KEY_NFC_MISO).ok().flatten().unwrap_or(default.nfc_miso),
+        nfc_nss: nvs.get_u8(NVS_KEY_NFC_NSS).ok().flatten().unwrap_or(default.nfc_nss),
+        nfc_baud_hz: nvs.get_u32(NVS_KEY_NFC_BAUD).ok().flatten().unwrap_or(default.nfc_baud_hz),
+        nfc_read_baud_hz: nvs.get_u32(NVS_KEY_NFC_READ_BAUD).ok().flatten().unwrap_or(default.nfc_read_baud_hz),
+        nfc_bitbang_delay_us: nvs
+            .get_u32(NVS_KEY_NFC_BITBANG_DELAY_US)
+            .ok()
+            .flatten()
+            .unwrap_or(default.nfc_bitbang_delay_us),
+        scale_sda: nvs.get_u8(NVS_KEY_SCALE_SDA).ok().flatten().unwrap_or(default.scale_sda),
+        scale_scl: nvs.get_u8(NVS_KEY_SCALE_SCL).ok().flatten().unwrap_or(default.scale_scl),
+        scale_baud_hz: nvs.get_u32(NVS_KEY_SCALE_BAUD).ok().flatten().unwrap_or(default.scale_baud_hz),
+    };
+
+    if let Err(e) = config.validate() {
+        warn!("Saved board config failed pin validation ({:?}), using compiled defaults", e);
+        return default;
+    }
+
+    info!("Loaded board config from NVS: {:?}", config);
+    config
+}
+
+fn save(nvs_partition: &EspDefaultNvsPartition, config: &BoardConfig) -> Result<(), String> {
+    let mut nvs = EspNvs::new(nvs_partition.clone(), NVS_NAMESPACE, true)
+        .map_err(|e| format!("Failed to open {} NVS namespace: {:?}", NVS_NAMESPACE, e))?;
+
+    nvs.set_u8(NVS_KEY_NFC_SPI_HOST, config.nfc_spi_host).map_err(|e| format!("{:?}", e))?;
+    nvs.set_u8(NVS_KEY_NFC_SCK, config.nfc_sck).map_err(|e| format!("{:?}", e))?;
+    nvs.set_u8(NVS_KEY_NFC_MOSI, config.nfc_mosi).map_err(|e| format!("{:?}", e))?;
+    nvs.set_u8(NVS_KEY_NFC_MISO, config.nfc_miso).map_err(|e| format!("{:?}", e))?;
+    nvs.set_u8(NVS_KEY_NFC_NSS, config.nfc_nss).map_err(|e| format!("{:?}", e))?;
+    nvs.set_u32(NVS_KEY_NFC_BAUD, config.nfc_baud_hz).map_err(|e| format!("{:?}", e))?;
+    nvs.set_u32(NVS_KEY_NFC_READ_BAUD, config.nfc_read_baud_hz).map_err(|e| format!("{:?}", e))?;
+    nvs.set_u32(NVS_KEY_NFC_BITBANG_DELAY_US, config.nfc_bitbang_delay_us).map_err(|e| format!("{:?}", e))?;
+    nvs.set_u8(NVS_KEY_SCALE_SDA, config.scale_sda).map_err(|e| format!("{:?}", e))?;
+    nvs.set_u8(NVS_KEY_SCALE_SCL, config.scale_scl).map_err(|e| format!("{:?}", e))?;
+    nvs.set_u32(NVS_KEY_SCALE_BAUD, config.scale_baud_hz).map_err(|e| format!("{:?}", e))?;
+
+    Ok(())
+}
+
+// ============================================================================
+// C-callable interface
+// ============================================================================
+
+/// Pin assignment and bus speed for C code (the settings/diagnostics screen).
+#[repr(C)]
+pub struct BoardConfigFfi {
+    /// 0=SPI2, 1=SPI3. See `BoardConfig::nfc_spi_host`.
+    pub nfc_spi_host: u8,
+    pub nfc_sck: u8,
+    pub nfc_mosi: u8,
+    pub nfc_miso: u8,
+    pub nfc_nss: u8,
+    pub nfc_baud_hz: u32,
+    pub nfc_read_baud_hz: u32,
+    pub nfc_bitbang_delay_us: u32,
+    pub scale_sda: u8,
+    pub scale_scl: u8,
+    pub scale_baud_hz: u32,
+}
+
+impl From<BoardConfig> for BoardConfigFfi {
+    fn from(c: BoardConfig) -> Self {
+        BoardConfigFfi {
+            nfc_spi_host: c.nfc_spi_host,
+            nfc_sck: c.nfc_sck,
+            nfc_mosi: c.nfc_mosi,
+            nfc_miso: c.nfc_miso,
+            nfc_nss: c.nfc_nss,
+            nfc_baud_hz: c.nfc_baud_hz,
+            nfc_read_baud_hz: c.nfc_read_baud_hz,
+            nfc_bitbang_delay_us: c.nfc_bitbang_delay_us,
+            scale_sda: c.scale_sda,
+            scale_scl: c.scale_scl,
+            scale_baud_hz: c.scale_baud_hz,
+        }
+    }
+}
+
+impl From<&BoardConfigFfi> for BoardConfig {
+    fn from(c: &BoardConfigFfi) -> Self {
+        BoardConfig {
+            nfc_spi_host: c.nfc_spi_host,
+            nfc_sck: c.nfc_sck,
+            nfc_mosi: c.nfc_mosi,
+            nfc_miso: c.nfc_miso,
+            nfc_nss: c.nfc_nss,
+            nfc_baud_hz: c.nfc_baud_hz,
+            nfc_read_baud_hz: c.nfc_read_baud_hz,
+            nfc_bitbang_delay_us: c.nfc_bitbang_delay_us,
+            scale_sda: c.scale_sda,
+            scale_scl: c.scale_scl,
+            scale_baud_hz: c.scale_baud_hz,
+        }
+    }
+}
+
+/// Get the board config currently in effect (the one `main()` built its
+/// buses from). Returns false if `init_board_config` hasn't run yet.
+#[no_mangle]
+pub extern "C" fn board_config_get(out: *mut BoardConfigFfi) -> bool {
+    if out.is_null() {
+        return false;
+    }
+
+    let Some(config) = *ACTIVE_CONFIG.lock().unwrap() else {
+        return false;
+    };
+
+    unsafe {
+        *out = config.into();
+    }
+    true
+}
+
+/// Validate a new pin assignment, persist it to NVS, and reboot so `main()`
+/// picks it up on the next boot. Returns 0 just before rebooting (the caller
+/// never actually sees this return on success), -1 if the assignment fails
+/// pin-capability validation, or -2 if it couldn't be saved (no NVS
+/// partition, or the write failed). On -1 nothing is written; on a failed
+/// write, keys saved before the failure stay in NVS. In both error cases the
+/// board keeps running on its current config.
+#[no_mangle]
+#[allow(unreachable_code)]
+pub extern "C" fn board_config_apply(new_config: *const BoardConfigFfi) -> c_int {
+    if new_config.is_null() {
+        return -1;
+    }
+    let config: BoardConfig = unsafe { &*new_config }.into();
+
+    if let Err(e) = config.validate() {
+        warn!("Rejected board config remap: {:?}", e);
+        return -1;
+    }
+
+    let nvs_guard = NVS_PARTITION.lock().unwrap();
+    let Some(nvs_partition) = nvs_guard.as_ref() else {
+        warn!("No NVS partition available, can't persist board config remap");
+        return -2;
+    };
+
+    if let Err(e) = save(nvs_partition, &config) {
+        warn!("Failed to save board config: {}", e);
+        return -2;
+    }
+    drop(nvs_guard);
+
+    info!("Board config saved, rebooting to apply new pin assignment...");
+    unsafe { esp_idf_sys::esp_restart() };
+    0
+}
@@ -0,0 +1,392 @@
+//! Structured hardware self-test.
+//!
+//! The GPIO-short, bit-bang, and loopback checks that used to run once at
+//! boot and only emit `info!`/`warn!` lines (invisible once the board is in
+//! its enclosure) are collected here into a `run_self_test()` that produces
+//! a queryable `SelfTestReport`. The report is cached so the UI
+//! (`diagnostics_get_result`) and `wifi_manager` (`wifi_get_diagnostics_json`)
+//! can ask "is the board healthy?" at any time, not just in the boot log.
+
+use std::ffi::{c_char, c_int};
+use std::sync::Mutex;
+
+use esp_idf_hal::delay::FreeRtos;
+use esp_idf_hal::gpio::{PinDriver, Pull};
+use esp_idf_hal::i2c::I2cDriver;
+use log::info;
+
+use crate::bitbang_spi::{BitBangSpi, BitBangSpiConfig};
+use crate::board_config::BoardConfig;
+use crate::nfc::pn5180::Pn5180Driver;
+use crate::pin_caps;
+use crate::scale::nau7802;
+
+/// Outcome of a single hardware check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticOutcome {
+    Pass,
+    Fail,
+    Skipped,
+}
+
+/// Result of one named diagnostic check.
+#[derive(Debug, Clone)]
+pub struct DiagnosticResult {
+    pub name: &'static str,
+    pub outcome: DiagnosticOutcome,
+    pub detail: String,
+}
+
+impl DiagnosticResult {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        DiagnosticResult { name, outcome: DiagnosticOutcome::Pass, detail: detail.into() }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>) -> Self {
+        DiagnosticResult { name, outcome: DiagnosticOutcome::Fail, detail: detail.into() }
+    }
+
+    fn skipped(name: &'static str, detail: impl Into<String>) -> Self {
+        DiagnosticResult { name, outcome: DiagnosticOutcome::Skipped, detail: detail.into() }
+    }
+}
+
+/// A full board self-test run: one `DiagnosticResult` per check, in the
+/// order the checks ran.
+#[derive(Debug, Clone, Default)]
+pub struct SelfTestReport {
+    pub results: Vec<DiagnosticResult>,
+}
+
+impl SelfTestReport {
+    /// Hand-rolled JSON encoding; every string is passed through
+    /// `escape_json`.
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{\"results\":[");
+        for (i, result) in self.results.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            let outcome = match result.outcome {
+                DiagnosticOutcome::Pass => "pass",
+                DiagnosticOutcome::Fail => "fail",
+                DiagnosticOutcome::Skipped => "skipped",
+            };
+            json.push_str(&format!(
+                "{{\"name\":\"{}\",\"outcome\":\"{}\",\"detail\":\"{}\"}}",
+                escape_json(result.name),
+                outcome,
+                escape_json(&result.detail),
+            ));
+        }
+        json.push_str("]}");
+        json
+    }
+}
+
+/// Escape a string for embedding in the hand-rolled JSON below. Beyond `\`
+/// and `"`, also escapes raw control bytes (e.g. a literal newline/tab
+/// that ended up in a `detail` string via `format!("{:?}", e)`) as `\u%04x`
+/// so a `Debug` impl that isn't ASCII-clean can't produce invalid JSON.
+fn escape_json(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+static LAST_REPORT: Mutex<Option<SelfTestReport>> = Mutex::new(None);
+
+/// Most recently completed self-test, if `run_self_test` has ever been called.
+pub fn last_report() -> Option<SelfTestReport> {
+    LAST_REPORT.lock().unwrap().clone()
+}
+
+/// Run every hardware check and cache the report. Takes the scale's I2C1
+/// driver by reference (it's still needed afterwards for the real NAU7802
+/// init) and the resolved `BoardConfig` so the NFC checks probe whatever
+/// GPIOs `board_config_apply` last pointed the SPI bus at, not a hardcoded
+/// default; the NFC GPIOs are `steal()`d (via `pin_caps`) and released
+/// before returning, same as the ad-hoc boot tests this replaces, so the
+/// caller's subsequent "real" SPI/GPIO setup isn't affected.
+pub fn run_self_test(scale_i2c: Option<&mut I2cDriver<'static>>, board_config: &BoardConfig) -> SelfTestReport {
+    info!("=== HARDWARE SELF-TEST ===");
+
+    let results = vec![
+        check_touch_i2c0(),
+        check_scale_i2c1(scale_i2c),
+        check_gpio4_6_short(board_config),
+        check_miso_stuck_low(board_config),
+        check_sck_mosi_loopback(board_config),
+        check_pn5180_firmware_read(board_config),
+    ];
+
+    for result in &results {
+        info!("  [{:?}] {}: {}", result.outcome, result.name, result.detail);
+    }
+
+    let report = SelfTestReport { results };
+    *LAST_REPORT.lock().unwrap() = Some(report.clone());
+    report
+}
+
+fn check_touch_i2c0() -> DiagnosticResult {
+    DiagnosticResult::skipped(
+        "i2c0_touch_present",
+        "I2C0 (touch controller) is owned by the C display driver, not queryable from Rust",
+    )
+}
+
+fn check_scale_i2c1(i2c: Option<&mut I2cDriver<'static>>) -> DiagnosticResult {
+    let Some(i2c) = i2c else {
+        return DiagnosticResult::skipped("i2c1_nau7802_present", "I2C1 bus not initialized");
+    };
+
+    for addr in 0x08..0x78 {
+        let mut buf = [0u8; 1];
+        if i2c.read(addr, &mut buf, 100).is_ok() && addr == nau7802::NAU7802_ADDR {
+            return DiagnosticResult::pass("i2c1_nau7802_present", format!("Responded at 0x{:02X}", addr));
+        }
+    }
+    DiagnosticResult::fail("i2c1_nau7802_present", format!("No response at 0x{:02X}", nau7802::NAU7802_ADDR))
+}
+
+fn check_gpio4_6_short(board_config: &BoardConfig) -> DiagnosticResult {
+    let gpio_mosi = match pin_caps::any_output_pin(board_config.nfc_mosi) {
+        Ok(pin) => pin,
+        Err(e) => return DiagnosticResult::fail("gpio4_6_short", format!("GPIO{} acquisition failed: {:?}", board_config.nfc_mosi, e)),
+    };
+    let gpio_miso = match pin_caps::any_input_pin(board_config.nfc_miso) {
+        Ok(pin) => pin,
+        Err(e) => return DiagnosticResult::fail("gpio4_6_short", format!("GPIO{} acquisition failed: {:?}", board_config.nfc_miso, e)),
+    };
+
+    let mut mosi_out = match PinDriver::output(gpio_mosi) {
+        Ok(pin) => pin,
+        Err(e) => return DiagnosticResult::fail("gpio4_6_short", format!("GPIO{} init failed: {:?}", board_config.nfc_mosi, e)),
+    };
+    let miso_in = match PinDriver::input(gpio_miso, Pull::Floating) {
+        Ok(pin) => pin,
+        Err(e) => return DiagnosticResult::fail("gpio4_6_short", format!("GPIO{} init failed: {:?}", board_config.nfc_miso, e)),
+    };
+
+    mosi_out.set_high().ok();
+    FreeRtos::delay_ms(10);
+    let read_high = miso_in.is_high();
+
+    mosi_out.set_low().ok();
+    FreeRtos::delay_ms(10);
+    let read_low = miso_in.is_low();
+
+    if read_high && read_low {
+        DiagnosticResult::fail(
+            "gpio4_6_short",
+            format!(
+                "GPIO{} (MISO) and GPIO{} (MOSI) are shorted together on this board",
+                board_config.nfc_miso, board_config.nfc_mosi
+            ),
+        )
+    } else {
+        DiagnosticResult::pass(
+            "gpio4_6_short",
+            format!("GPIO{} and GPIO{} toggle independently", board_config.nfc_miso, board_config.nfc_mosi),
+        )
+    }
+}
+
+fn check_miso_stuck_low(board_config: &BoardConfig) -> DiagnosticResult {
+    let gpio_miso = match pin_caps::any_input_pin(board_config.nfc_miso) {
+        Ok(pin) => pin,
+        Err(e) => return DiagnosticResult::fail("miso_stuck_low", format!("GPIO{} acquisition failed: {:?}", board_config.nfc_miso, e)),
+    };
+    let miso_pullup = match PinDriver::input(gpio_miso, Pull::Up) {
+        Ok(pin) => pin,
+        Err(e) => return DiagnosticResult::fail("miso_stuck_low", format!("GPIO{} init failed: {:?}", board_config.nfc_miso, e)),
+    };
+
+    FreeRtos::delay_ms(10);
+    if miso_pullup.is_high() {
+        DiagnosticResult::pass("miso_stuck_low", format!("GPIO{} (MISO) reads HIGH with internal pull-up", board_config.nfc_miso))
+    } else {
+        DiagnosticResult::fail(
+            "miso_stuck_low",
+            format!("GPIO{} (MISO) reads LOW even with internal pull-up - shorted to GND", board_config.nfc_miso),
+        )
+    }
+}
+
+fn check_sck_mosi_loopback(board_config: &BoardConfig) -> DiagnosticResult {
+    let gpio_miso = match pin_caps::any_input_pin(board_config.nfc_miso) {
+        Ok(pin) => pin,
+        Err(e) => return DiagnosticResult::fail("sck_mosi_loopback", format!("GPIO{} acquisition failed: {:?}", board_config.nfc_miso, e)),
+    };
+    let gpio_sck = match pin_caps::any_output_pin(board_config.nfc_sck) {
+        Ok(pin) => pin,
+        Err(e) => return DiagnosticResult::fail("sck_mosi_loopback", format!("GPIO{} acquisition failed: {:?}", board_config.nfc_sck, e)),
+    };
+    let gpio_mosi = match pin_caps::any_output_pin(board_config.nfc_mosi) {
+        Ok(pin) => pin,
+        Err(e) => return DiagnosticResult::fail("sck_mosi_loopback", format!("GPIO{} acquisition failed: {:?}", board_config.nfc_mosi, e)),
+    };
+
+    let miso = match PinDriver::input(gpio_miso, Pull::Up) {
+        Ok(pin) => pin,
+        Err(e) => return DiagnosticResult::fail("sck_mosi_loopback", format!("GPIO{} init failed: {:?}", board_config.nfc_miso, e)),
+    };
+    let mut sck = match PinDriver::output(gpio_sck) {
+        Ok(pin) => pin,
+        Err(e) => return DiagnosticResult::fail("sck_mosi_loopback", format!("GPIO{} init failed: {:?}", board_config.nfc_sck, e)),
+    };
+    let mut mosi = match PinDriver::output(gpio_mosi) {
+        Ok(pin) => pin,
+        Err(e) => return DiagnosticResult::fail("sck_mosi_loopback", format!("GPIO{} init failed: {:?}", board_config.nfc_mosi, e)),
+    };
+
+    // Requires a test-jig wire shorting J9-Pin2 (SCK) or J9-Pin4 (MOSI) to
+    // J9-Pin3 (MISO); absent that wire, neither loopback is expected to pass.
+    let mut sck_ok = true;
+    for i in 0..4 {
+        let expected = i % 2 == 0;
+        if expected { sck.set_high().ok() } else { sck.set_low().ok() };
+        FreeRtos::delay_ms(2);
+        if miso.is_high() != expected {
+            sck_ok = false;
+        }
+    }
+    sck.set_low().ok();
+
+    let mut mosi_ok = true;
+    for i in 0..4 {
+        let expected = i % 2 == 0;
+        if expected { mosi.set_high().ok() } else { mosi.set_low().ok() };
+        FreeRtos::delay_ms(2);
+        if miso.is_high() != expected {
+            mosi_ok = false;
+        }
+    }
+    mosi.set_low().ok();
+
+    match (sck_ok, mosi_ok) {
+        (true, true) => DiagnosticResult::pass("sck_mosi_loopback", "SCK->MISO and MOSI->MISO loopbacks both responded"),
+        (true, false) => DiagnosticResult::fail("sck_mosi_loopback", "SCK loopback ok, MOSI loopback failed"),
+        (false, true) => DiagnosticResult::fail("sck_mosi_loopback", "MOSI loopback ok, SCK loopback failed"),
+        (false, false) => {
+            DiagnosticResult::skipped("sck_mosi_loopback", "Neither loopback responded - no test-jig wire present")
+        }
+    }
+}
+
+fn check_pn5180_firmware_read(board_config: &BoardConfig) -> DiagnosticResult {
+    let gpio_sck = match pin_caps::any_output_pin(board_config.nfc_sck) {
+        Ok(pin) => pin,
+        Err(e) => return DiagnosticResult::fail("pn5180_firmware_read", format!("GPIO{} acquisition failed: {:?}", board_config.nfc_sck, e)),
+    };
+    let gpio_mosi = match pin_caps::any_output_pin(board_config.nfc_mosi) {
+        Ok(pin) => pin,
+        Err(e) => return DiagnosticResult::fail("pn5180_firmware_read", format!("GPIO{} acquisition failed: {:?}", board_config.nfc_mosi, e)),
+    };
+    let gpio_miso = match pin_caps::any_input_pin(board_config.nfc_miso) {
+        Ok(pin) => pin,
+        Err(e) => return DiagnosticResult::fail("pn5180_firmware_read", format!("GPIO{} acquisition failed: {:?}", board_config.nfc_miso, e)),
+    };
+    let gpio_nss = match pin_caps::any_output_pin(board_config.nfc_nss) {
+        Ok(pin) => pin,
+        Err(e) => return DiagnosticResult::fail("pn5180_firmware_read", format!("GPIO{} acquisition failed: {:?}", board_config.nfc_nss, e)),
+    };
+
+    let sck = match PinDriver::output(gpio_sck) {
+        Ok(pin) => pin,
+        Err(e) => return DiagnosticResult::fail("pn5180_firmware_read", format!("GPIO{} init failed: {:?}", board_config.nfc_sck, e)),
+    };
+    let mosi = match PinDriver::output(gpio_mosi) {
+        Ok(pin) => pin,
+        Err(e) => return DiagnosticResult::fail("pn5180_firmware_read", format!("GPIO{} init failed: {:?}", board_config.nfc_mosi, e)),
+    };
+    let miso = match PinDriver::input(gpio_miso, Pull::Up) {
+        Ok(pin) => pin,
+        Err(e) => return DiagnosticResult::fail("pn5180_firmware_read", format!("GPIO{} init failed: {:?}", board_config.nfc_miso, e)),
+    };
+    let mut nss = match PinDriver::output(gpio_nss) {
+        Ok(pin) => pin,
+        Err(e) => return DiagnosticResult::fail("pn5180_firmware_read", format!("GPIO{} init failed: {:?}", board_config.nfc_nss, e)),
+    };
+    nss.set_high().ok();
+    FreeRtos::delay_ms(100); // let the PN5180 finish powering on
+
+    let spi = BitBangSpi::new(sck, mosi, miso, BitBangSpiConfig::default());
+    let mut driver = Pn5180Driver { spi, nss, busy: None, rst: None };
+
+    match driver.get_firmware_version() {
+        Ok((0, 0, 0)) => {
+            DiagnosticResult::fail("pn5180_firmware_read", "Firmware version read as 0.0.0 - PN5180 not responding")
+        }
+        Ok((major, minor, patch)) => {
+            DiagnosticResult::pass("pn5180_firmware_read", format!("Firmware {}.{}.{}", major, minor, patch))
+        }
+        Err(e) => DiagnosticResult::fail("pn5180_firmware_read", format!("SPI error: {:?}", e)),
+    }
+}
+
+// ============================================================================
+// C-callable interface
+// ============================================================================
+
+/// One diagnostic result for C code.
+#[repr(C)]
+pub struct DiagnosticResultFfi {
+    pub name: [c_char; 32],
+    /// 0=Pass, 1=Fail, 2=Skipped
+    pub outcome: c_int,
+    pub detail: [c_char; 96],
+}
+
+fn str_to_c_buf(src: &str, dst: &mut [c_char]) {
+    let bytes = src.as_bytes();
+    let copy_len = bytes.len().min(dst.len() - 1);
+    for (i, &b) in bytes[..copy_len].iter().enumerate() {
+        dst[i] = b as c_char;
+    }
+    dst[copy_len] = 0;
+}
+
+/// Number of results from the most recent self-test (0 if none has run yet).
+#[no_mangle]
+pub extern "C" fn diagnostics_get_result_count() -> usize {
+    last_report().map(|r| r.results.len()).unwrap_or(0)
+}
+
+/// Get one result from the most recent self-test for the diagnostics screen.
+/// Returns false if `idx` is out of range or no self-test has run yet.
+#[no_mangle]
+pub extern "C" fn diagnostics_get_result(idx: usize, out: *mut DiagnosticResultFfi) -> bool {
+    if out.is_null() {
+        return false;
+    }
+
+    let Some(report) = last_report() else {
+        return false;
+    };
+    let Some(result) = report.results.get(idx) else {
+        return false;
+    };
+
+    unsafe {
+        str_to_c_buf(result.name, &mut (*out).name);
+        (*out).outcome = match result.outcome {
+            DiagnosticOutcome::Pass => 0,
+            DiagnosticOutcome::Fail => 1,
+            DiagnosticOutcome::Skipped => 2,
+        };
+        str_to_c_buf(&result.detail, &mut (*out).detail);
+    }
+    true
+}
@@ -0,0 +1,130 @@
+//! Host Card Emulation: present the PN5180 as an ISO14443-A Type 2 tag so a
+//! phone can tap and read SpoolBuddy's live state, instead of the device
+//! always being the reader.
+//!
+//! This builds a small NDEF message in RAM (a URI record plus an external
+//! record carrying weight/material) and serves it out of the NTAG-style
+//! page layout when a reader activates the field, mirroring the
+//! `set_tag_emulation_message` pattern used by the ESPHome PN71xx component.
+
+use embedded_hal::spi::SpiDevice;
+
+use super::ndef::{tnf, URI_PREFIXES};
+use super::pn5180::{Pn5180Driver, Pn5180Error};
+
+/// First user-memory page emulated NDEF content is served from (matches the
+/// NTAG21x layout the reader path in `ndef.rs` assumes).
+const FIRST_USER_PAGE: u8 = 4;
+
+/// Build the byte buffer to serve as tag memory: a TLV-wrapped NDEF message
+/// containing a URI record and an external-type record, followed by the
+/// Terminator TLV a reader expects.
+pub fn build_emulation_memory(uri: &str, external_type: &str, payload: &[u8]) -> Vec<u8> {
+    let mut message = Vec::new();
+    encode_uri_record(uri, false, &mut message);
+    encode_external_record(external_type, payload, true, &mut message);
+
+    let mut memory = Vec::with_capacity(message.len() + 4);
+    memory.push(0x03); // NDEF Message TLV
+    if message.len() < 0xFF {
+        memory.push(message.len() as u8);
+    } else {
+        memory.push(0xFF);
+        memory.push((message.len() >> 8) as u8);
+        memory.push((message.len() & 0xFF) as u8);
+    }
+    memory.extend_from_slice(&message);
+    memory.push(0xFE); // Terminator TLV
+
+    memory
+}
+
+/// Append a well-known URI record, abbreviating the longest matching prefix.
+fn encode_uri_record(uri: &str, message_end: bool, out: &mut Vec<u8>) {
+    let (prefix_code, rest) = best_uri_prefix(uri);
+
+    let mut payload = Vec::with_capacity(1 + rest.len());
+    payload.push(prefix_code);
+    payload.extend_from_slice(rest.as_bytes());
+
+    encode_record(tnf::WELL_KNOWN, b"U", &payload, true, message_end, out);
+}
+
+/// Append an external-type record (e.g. `spoolbuddy.io:spool`) carrying arbitrary bytes.
+fn encode_external_record(external_type: &str, payload: &[u8], message_end: bool, out: &mut Vec<u8>) {
+    encode_record(tnf::EXTERNAL, external_type.as_bytes(), payload, false, message_end, out);
+}
+
+/// Find the longest URI abbreviation prefix that matches `uri`, returning its
+/// code and the remainder of the string. Falls back to code 0 (no abbreviation).
+fn best_uri_prefix(uri: &str) -> (u8, &str) {
+    let mut best_code = 0u8;
+    let mut best_len = 0usize;
+
+    for (code, prefix) in URI_PREFIXES.iter().enumerate() {
+        if !prefix.is_empty() && uri.starts_with(prefix) && prefix.len() > best_len {
+            best_code = code as u8;
+            best_len = prefix.len();
+        }
+    }
+
+    (best_code, &uri[best_len..])
+}
+
+/// Encode one NDEF record header + type/length/payload fields.
+fn encode_record(tnf: u8, record_type: &[u8], payload: &[u8], message_begin: bool, message_end: bool, out: &mut Vec<u8>) {
+    let short_record = payload.len() < 0x100;
+
+    let mut header = tnf & 0x07;
+    if message_begin {
+        header |= 0x80; // MB
+    }
+    if message_end {
+        header |= 0x40; // ME
+    }
+    if short_record {
+        header |= 0x10; // SR
+    }
+
+    out.push(header);
+    out.push(record_type.len() as u8);
+    if short_record {
+        out.push(payload.len() as u8);
+    } else {
+        out.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    }
+    out.extend_from_slice(record_type);
+    out.extend_from_slice(payload);
+}
+
+/// Configure the PN5180 to answer ISO14443-A reader commands as a Type 2 Tag.
+/// The caller (the NFC manager) keeps owning the emulated memory buffer and
+/// passes it to `Pn5180Driver::serve_listen_frame` on each poll.
+pub fn enable_emulation<SPI>(driver: &mut Pn5180Driver<'_, SPI>) -> Result<(), Pn5180Error>
+where
+    SPI: SpiDevice,
+{
+    driver.load_rf_config_target_mode()
+}
+
+/// Disable emulation and return the PN5180 to idle.
+pub fn disable_emulation<SPI>(driver: &mut Pn5180Driver<'_, SPI>) -> Result<(), Pn5180Error>
+where
+    SPI: SpiDevice,
+{
+    driver.rf_off()
+}
+
+/// Read page `start_page` (4 bytes) out of the emulated tag memory, the way
+/// `ndef.rs`'s reader path reads a real NTAG. Returns `None` past the end of
+/// the buffer (the reader would see all-zero padding on a real tag).
+pub fn read_emulated_page(memory: &[u8], start_page: u8) -> Option<[u8; 4]> {
+    let offset = (start_page.checked_sub(FIRST_USER_PAGE)? as usize) * 4;
+    if offset >= memory.len() {
+        return None;
+    }
+    let mut page = [0u8; 4];
+    let available = &memory[offset..memory.len().min(offset + 4)];
+    page[..available.len()].copy_from_slice(available);
+    Some(page)
+}
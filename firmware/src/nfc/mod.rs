@@ -0,0 +1,8 @@
+//! NFC module for the PN5180 reader/writer IC.
+//!
+//! `pn5180` holds the SPI driver and ISO14443-A activation/transport logic.
+//! `ndef` decodes the NDEF Type 2 Tag layout carried on NTAG-class cards.
+
+pub mod emulation;
+pub mod ndef;
+pub mod pn5180;
@@ -0,0 +1,271 @@
+//! NDEF (NFC Data Exchange Format) parsing for Type 2 Tags (NTAG).
+//!
+//! Walks the TLV stream in NTAG user memory looking for the NDEF Message
+//! TLV, then decodes the NDEF record header format described in the NFC
+//! Forum NDEF spec. Only well-known URI and Text records are decoded into
+//! display text; everything else is still surfaced with its raw TNF/type/
+//! payload so callers can handle it themselves.
+
+use embedded_hal::spi::SpiDevice;
+
+use super::pn5180::{Pn5180Driver, Pn5180Error};
+
+/// NTAG Type 2 Tag TLV tags (NFC Forum Type 2 Tag Operation spec).
+mod tlv {
+    pub const NULL: u8 = 0x00;
+    pub const NDEF_MESSAGE: u8 = 0x03;
+    pub const TERMINATOR: u8 = 0xFE;
+}
+
+/// NDEF Type Name Format values (NFC Forum NDEF spec, section 3.2.6).
+pub(crate) mod tnf {
+    pub const WELL_KNOWN: u8 = 0x01;
+    pub const EXTERNAL: u8 = 0x04;
+}
+
+/// URI abbreviation table (NFC Forum URI Record Type Definition, table 3).
+pub(crate) const URI_PREFIXES: &[&str] = &[
+    "",
+    "http://www.",
+    "https://www.",
+    "http://",
+    "https://",
+    "tel:",
+    "mailto:",
+    "ftp://anonymous:anonymous@",
+    "ftp://ftp.",
+    "ftps://",
+    "sftp://",
+    "smb://",
+    "nfs://",
+    "ftp://",
+    "dav://",
+    "news:",
+    "telnet://",
+    "imap:",
+    "rtsp://",
+    "urn:",
+    "pop:",
+    "sip:",
+    "sips:",
+    "tftp:",
+    "btspp://",
+    "btl2cap://",
+    "btgoep://",
+    "tcpobex://",
+    "irdaobex://",
+    "file://",
+    "urn:epc:id:",
+    "urn:epc:tag:",
+    "urn:epc:pat:",
+    "urn:epc:raw:",
+    "urn:epc:",
+    "urn:nfc:",
+];
+
+/// Number of 4-byte pages read per NTAG READ command.
+const PAGES_PER_READ: u8 = 4;
+/// Hard stop so a corrupt/endless tag can't keep the poll loop reading forever.
+const MAX_USER_MEMORY_BYTES: usize = 1024;
+
+/// Errors that can occur while reading or decoding an NDEF message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NdefError {
+    /// A READ command to the tag failed.
+    Read(Pn5180Error),
+    /// The TLV or NDEF record structure is malformed or truncated.
+    Malformed,
+    /// No NDEF Message TLV was present on the tag.
+    NotFound,
+}
+
+impl From<Pn5180Error> for NdefError {
+    fn from(e: Pn5180Error) -> Self {
+        NdefError::Read(e)
+    }
+}
+
+/// A decoded NDEF record.
+#[derive(Debug, Clone)]
+pub struct NdefRecord {
+    /// Type Name Format (bits 2-0 of the record header).
+    pub tnf: u8,
+    /// Record type, e.g. `"U"` (URI) or `"T"` (Text).
+    pub record_type: String,
+    /// Raw payload bytes, as stored on the tag.
+    pub payload: Vec<u8>,
+    /// Decoded display text, when the record is a recognized URI or Text record.
+    pub text: Option<String>,
+}
+
+/// Read user memory from an NTAG card and return the decoded NDEF records.
+///
+/// Issues NTAG READ (`0x30`) commands starting at page 4 (the first user
+/// memory page on NTAG21x) and stops as soon as the Terminator TLV is seen,
+/// a read fails, or `MAX_USER_MEMORY_BYTES` have been collected.
+pub fn read_ndef<SPI>(driver: &mut Pn5180Driver<'_, SPI>) -> Result<Vec<NdefRecord>, NdefError>
+where
+    SPI: SpiDevice,
+{
+    let memory = read_user_memory(driver)?;
+    let message = find_ndef_message(&memory).ok_or(NdefError::NotFound)?;
+    parse_ndef_message(message)
+}
+
+/// Read pages from a NTAG card until a Terminator TLV is seen or a read fails.
+fn read_user_memory<SPI>(driver: &mut Pn5180Driver<'_, SPI>) -> Result<Vec<u8>, NdefError>
+where
+    SPI: SpiDevice,
+{
+    let mut memory = Vec::new();
+    let mut page = 4u8;
+
+    while memory.len() < MAX_USER_MEMORY_BYTES {
+        let chunk = driver.read_ntag_pages(page)?;
+        memory.extend_from_slice(&chunk);
+        page = page.saturating_add(PAGES_PER_READ);
+
+        if chunk.contains(&tlv::TERMINATOR) {
+            break;
+        }
+    }
+
+    Ok(memory)
+}
+
+/// Walk the TLV stream and return the bytes of the first NDEF Message TLV.
+fn find_ndef_message(memory: &[u8]) -> Option<&[u8]> {
+    let mut pos = 0;
+
+    while pos < memory.len() {
+        let tag = memory[pos];
+        pos += 1;
+
+        match tag {
+            tlv::NULL => continue,
+            tlv::TERMINATOR => break,
+            tlv::NDEF_MESSAGE => {
+                let (len, len_bytes) = read_tlv_length(&memory[pos..])?;
+                pos += len_bytes;
+                let end = pos.checked_add(len)?;
+                return memory.get(pos..end);
+            }
+            _other => {
+                // Unknown/unsupported TLV (e.g. Lock Control, Memory Control):
+                // skip over it using its length field.
+                let (len, len_bytes) = read_tlv_length(&memory[pos..])?;
+                pos += len_bytes + len;
+            }
+        }
+    }
+
+    None
+}
+
+/// Decode a TLV length field: one byte, or `0xFF` followed by a big-endian u16.
+fn read_tlv_length(data: &[u8]) -> Option<(usize, usize)> {
+    let first = *data.first()?;
+    if first == 0xFF {
+        let hi = *data.get(1)? as usize;
+        let lo = *data.get(2)? as usize;
+        Some(((hi << 8) | lo, 3))
+    } else {
+        Some((first as usize, 1))
+    }
+}
+
+/// Decode the sequence of NDEF records inside a Message TLV's value.
+fn parse_ndef_message(mut data: &[u8]) -> Result<Vec<NdefRecord>, NdefError> {
+    let mut records = Vec::new();
+
+    while !data.is_empty() {
+        let (record, rest) = parse_one_record(data)?;
+        let is_last = record.message_end;
+        records.push(record.record);
+        data = rest;
+        if is_last {
+            break;
+        }
+    }
+
+    Ok(records)
+}
+
+struct ParsedRecord {
+    record: NdefRecord,
+    message_end: bool,
+}
+
+fn parse_one_record(data: &[u8]) -> Result<(ParsedRecord, &[u8]), NdefError> {
+    let header = *data.first().ok_or(NdefError::Malformed)?;
+    let message_end = header & 0x40 != 0; // ME bit
+    let short_record = header & 0x10 != 0; // SR bit
+    let has_id = header & 0x08 != 0; // IL bit
+    let record_tnf = header & 0x07;
+
+    let mut pos = 1;
+    let type_len = *data.get(pos).ok_or(NdefError::Malformed)? as usize;
+    pos += 1;
+
+    let payload_len = if short_record {
+        let len = *data.get(pos).ok_or(NdefError::Malformed)? as usize;
+        pos += 1;
+        len
+    } else {
+        let bytes = data.get(pos..pos + 4).ok_or(NdefError::Malformed)?;
+        pos += 4;
+        u32::from_be_bytes(bytes.try_into().unwrap()) as usize
+    };
+
+    let id_len = if has_id {
+        let len = *data.get(pos).ok_or(NdefError::Malformed)? as usize;
+        pos += 1;
+        len
+    } else {
+        0
+    };
+
+    let type_bytes = data.get(pos..pos + type_len).ok_or(NdefError::Malformed)?;
+    pos += type_len;
+
+    pos += id_len; // ID bytes aren't surfaced today; just skip past them.
+
+    let payload_end = pos.checked_add(payload_len).ok_or(NdefError::Malformed)?;
+    let payload = data.get(pos..payload_end).ok_or(NdefError::Malformed)?;
+    pos = payload_end;
+
+    let record_type = String::from_utf8_lossy(type_bytes).into_owned();
+    let text = decode_well_known_payload(record_tnf, &record_type, payload);
+
+    let record = NdefRecord {
+        tnf: record_tnf,
+        record_type,
+        payload: payload.to_vec(),
+        text,
+    };
+
+    Ok((ParsedRecord { record, message_end }, &data[pos..]))
+}
+
+/// Decode URI and Text well-known records into display text.
+fn decode_well_known_payload(tnf: u8, record_type: &str, payload: &[u8]) -> Option<String> {
+    if tnf != tnf::WELL_KNOWN {
+        return None;
+    }
+
+    match record_type {
+        "U" => {
+            let prefix_code = *payload.first()? as usize;
+            let prefix = URI_PREFIXES.get(prefix_code).copied().unwrap_or("");
+            let rest = std::str::from_utf8(payload.get(1..)?).ok()?;
+            Some(format!("{prefix}{rest}"))
+        }
+        "T" => {
+            let status = *payload.first()?;
+            let lang_len = (status & 0x3F) as usize;
+            let text_bytes = payload.get(1 + lang_len..)?;
+            std::str::from_utf8(text_bytes).ok().map(str::to_owned)
+        }
+        _ => None,
+    }
+}
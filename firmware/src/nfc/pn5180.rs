@@ -0,0 +1,515 @@
+//! PN5180 NFC frontend driver.
+//!
+//! Talks to the NXP PN5180 over SPI using the framed two-phase protocol the
+//! chip expects: a command phase (NSS low while the command bytes are
+//! clocked out), a short guard delay while the chip processes the command,
+//! then a response phase (NSS low again while the reply is clocked in).
+//!
+//! Pins are wired to the CrowPanel J9/J11 headers used by this board (see
+//! `main.rs`/`board_config`): NSS=GPIO8 by default (remappable), BUSY=GPIO2.
+//! RST is not connected on this board revision, so init performs no reset
+//! and only waits out the power-on settle time.
+
+use embedded_hal::spi::SpiDevice;
+use esp_idf_hal::delay::FreeRtos;
+use esp_idf_hal::gpio::{AnyOutputPin, Gpio2, Input, Output, PinDriver};
+
+/// PN5180 command opcodes (see NXP PN5180 datasheet, section "Host Interface").
+mod cmd {
+    pub const WRITE_REGISTER: u8 = 0x00;
+    pub const READ_REGISTER: u8 = 0x04;
+    pub const READ_EEPROM: u8 = 0x07;
+    pub const SEND_DATA: u8 = 0x09;
+    pub const READ_DATA: u8 = 0x0A;
+    pub const LOAD_RF_CONFIG: u8 = 0x11;
+    pub const RF_ON: u8 = 0x16;
+    pub const RF_OFF: u8 = 0x17;
+    pub const MIFARE_AUTHENTICATE: u8 = 0x0C;
+}
+
+/// ISO14443 Type 2 Tag (NTAG) command opcodes, sent over RF via `rf_exchange`.
+mod tag_cmd {
+    pub const READ: u8 = 0x30;
+    pub const WRITE: u8 = 0xA2;
+}
+
+/// MIFARE Classic key type, as passed to `MIFARE_AUTHENTICATE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MifareKeyType {
+    A,
+    B,
+}
+
+/// The standard (non-secret) NDEF key used to format MIFARE Classic tags for NDEF storage.
+pub const MIFARE_NDEF_KEY_A: [u8; 6] = [0xA0, 0xA1, 0xA2, 0xA3, 0xA4, 0xA5];
+
+/// 4-bit ACK for an NTAG WRITE, read back as a single byte.
+const NTAG_WRITE_ACK: u8 = 0x0A;
+
+/// RF status register (bit 0 = external field detected while in target/listen
+/// mode; bits 23:16 = AGC (automatic gain control) value, a proxy for antenna
+/// tuning/field strength).
+const REG_RF_STATUS: u8 = 0x1D;
+
+/// EEPROM address of the firmware version (patch in the first byte, major.minor
+/// as two nibbles in the second).
+const EEPROM_ADDR_FIRMWARE_VERSION: u8 = 0x10;
+
+/// Delay between the command phase and the response phase of a framed transaction.
+const FRAME_GUARD_DELAY_MS: u32 = 2;
+
+/// Errors that can occur while talking to the PN5180.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pn5180Error {
+    /// The underlying SPI transfer failed.
+    Spi,
+    /// Driving the NSS/BUSY/RST GPIO failed.
+    Gpio,
+    /// No card responded within the expected window.
+    Timeout,
+    /// The card replied but the frame failed a CRC/length sanity check.
+    Crc,
+    /// A register write did not read back as expected.
+    RegisterMismatch,
+    /// An NTAG WRITE was not acknowledged by the card.
+    WriteNotAcked,
+    /// MIFARE Classic authentication was rejected by the card.
+    AuthFailed,
+}
+
+/// Tracked state for the PN5180, shared with the NFC manager.
+#[derive(Debug, Clone, Default)]
+pub struct Pn5180State {
+    /// Set once `init_pn5180` has successfully read back a firmware version.
+    pub initialized: bool,
+    /// (major, minor, patch) as decoded from EEPROM.
+    pub firmware_version: (u8, u8, u8),
+}
+
+impl Pn5180State {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// A card activated via `iso14443a_activate`.
+#[derive(Debug, Clone)]
+pub struct Iso14443aCard {
+    /// UID bytes, left-aligned; only the first `uid_len` are valid.
+    pub uid: [u8; 10],
+    /// UID length: 4 (single size), 7 (double size), or 10 (triple size).
+    pub uid_len: u8,
+    /// ATQA bytes as returned by REQA.
+    pub atqa: [u8; 2],
+    /// SAK byte as returned by the final SELECT.
+    pub sak: u8,
+}
+
+/// Result of `Pn5180Driver::run_self_test`. Each check is attempted
+/// independently so one failure (e.g. no antenna connected) doesn't hide
+/// whether the rest of the link is healthy.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Pn5180SelfTestResult {
+    /// Firmware version EEPROM readback succeeded.
+    pub firmware_ok: bool,
+    /// (major, minor, patch), valid when `firmware_ok`.
+    pub firmware_version: (u8, u8, u8),
+    /// Scratch register write/readback loopback check succeeded.
+    pub register_loopback_ok: bool,
+    /// AGC value sampled with the RF field on; a low reading on a card-free
+    /// field suggests a weak or miscalibrated antenna.
+    pub agc_value: u8,
+    /// Whether the RF field could be turned on and the AGC register read.
+    pub agc_ok: bool,
+}
+
+impl Iso14443aCard {
+    /// NTAG21x reports SAK 0x00 with a 7-byte UID.
+    pub fn is_ntag(&self) -> bool {
+        self.sak == 0x00 && self.uid_len == 7
+    }
+
+    /// MIFARE Classic 1K reports SAK 0x08.
+    pub fn is_mifare_classic_1k(&self) -> bool {
+        self.sak == 0x08
+    }
+
+    /// MIFARE Classic 4K reports SAK 0x18.
+    pub fn is_mifare_classic_4k(&self) -> bool {
+        self.sak == 0x18
+    }
+}
+
+/// Driver for the PN5180, owning the SPI device and its manually-driven control pins.
+pub struct Pn5180Driver<'a, SPI> {
+    pub spi: SPI,
+    pub nss: PinDriver<'a, AnyOutputPin, Output>,
+    pub busy: Option<PinDriver<'a, Gpio2, Input>>,
+    pub rst: Option<PinDriver<'a, AnyOutputPin, Output>>,
+}
+
+impl<'a, SPI> Pn5180Driver<'a, SPI>
+where
+    SPI: SpiDevice,
+{
+    /// Run one framed command/response transaction.
+    ///
+    /// NSS is held low for the whole command phase, released for
+    /// `FRAME_GUARD_DELAY_MS` while the chip processes it, then pulled low
+    /// again to clock the response out.
+    fn transceive(&mut self, cmd: &[u8], response: &mut [u8]) -> Result<(), Pn5180Error> {
+        self.nss.set_low().map_err(|_| Pn5180Error::Gpio)?;
+        self.spi.write(cmd).map_err(|_| Pn5180Error::Spi)?;
+        self.nss.set_high().map_err(|_| Pn5180Error::Gpio)?;
+
+        if response.is_empty() {
+            return Ok(());
+        }
+
+        FreeRtos::delay_ms(FRAME_GUARD_DELAY_MS);
+
+        self.nss.set_low().map_err(|_| Pn5180Error::Gpio)?;
+        self.spi.transfer_in_place(response).map_err(|_| Pn5180Error::Spi)?;
+        self.nss.set_high().map_err(|_| Pn5180Error::Gpio)?;
+
+        Ok(())
+    }
+
+    /// Read `len` bytes from EEPROM starting at `addr`.
+    fn read_eeprom(&mut self, addr: u8, buf: &mut [u8]) -> Result<(), Pn5180Error> {
+        let cmd = [cmd::READ_EEPROM, addr, buf.len() as u8];
+        self.transceive(&cmd, buf)
+    }
+
+    /// Read the firmware version (major, minor, patch).
+    pub fn get_firmware_version(&mut self) -> Result<(u8, u8, u8), Pn5180Error> {
+        let mut version = [0u8; 2];
+        self.read_eeprom(EEPROM_ADDR_FIRMWARE_VERSION, &mut version)?;
+
+        let major = version[1] >> 4;
+        let minor = version[1] & 0x0F;
+        let patch = version[0];
+        Ok((major, minor, patch))
+    }
+
+    /// Enable the RF field (required before any card can be activated).
+    pub fn rf_on(&mut self) -> Result<(), Pn5180Error> {
+        self.transceive(&[cmd::RF_ON, 0x00], &mut [])
+    }
+
+    /// Disable the RF field.
+    pub fn rf_off(&mut self) -> Result<(), Pn5180Error> {
+        self.transceive(&[cmd::RF_OFF, 0x00], &mut [])
+    }
+
+    /// Write a 32-bit register (used by RF configuration and diagnostics).
+    pub fn write_register(&mut self, addr: u8, value: u32) -> Result<(), Pn5180Error> {
+        let bytes = value.to_le_bytes();
+        let cmd = [cmd::WRITE_REGISTER, addr, bytes[0], bytes[1], bytes[2], bytes[3]];
+        self.transceive(&cmd, &mut [])
+    }
+
+    /// Read a 32-bit register.
+    pub fn read_register(&mut self, addr: u8) -> Result<u32, Pn5180Error> {
+        let mut buf = [0u8; 4];
+        self.transceive(&[cmd::READ_REGISTER, addr], &mut buf)?;
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    /// Load the preconfigured ISO14443-A RF configuration (106 kbit/s).
+    fn load_rf_config_iso14443a(&mut self) -> Result<(), Pn5180Error> {
+        self.transceive(&[cmd::LOAD_RF_CONFIG, 0x00, 0x80], &mut [])
+    }
+
+    /// Transmit `tx` over RF and read up to `response.len()` bytes back.
+    fn rf_exchange(&mut self, tx: &[u8], response: &mut [u8]) -> Result<(), Pn5180Error> {
+        let mut send_cmd = Vec::with_capacity(tx.len() + 2);
+        send_cmd.push(cmd::SEND_DATA);
+        send_cmd.push(0x00);
+        send_cmd.extend_from_slice(tx);
+        self.transceive(&send_cmd, &mut [])?;
+
+        self.transceive(&[cmd::READ_DATA, 0x00], response)
+    }
+
+    /// Issue an NTAG Type 2 Tag READ (`0x30`): returns 4 pages (16 bytes)
+    /// starting at `start_page`. Used by the NDEF parser to walk user memory.
+    pub fn read_ntag_pages(&mut self, start_page: u8) -> Result<[u8; 16], Pn5180Error> {
+        let mut response = [0u8; 16];
+        self.rf_exchange(&[tag_cmd::READ, start_page], &mut response)?;
+        Ok(response)
+    }
+
+    /// Read `count` consecutive 4-byte NTAG pages starting at `start_page`,
+    /// issuing one READ per 4-page block. `count` need not be a multiple of 4.
+    pub fn read_ntag_page_range(&mut self, start_page: u8, count: u8) -> Result<Vec<u8>, Pn5180Error> {
+        let mut out = Vec::with_capacity(count as usize * 4);
+        let mut page = start_page;
+
+        while out.len() < count as usize * 4 {
+            let block = self.read_ntag_pages(page)?;
+            out.extend_from_slice(&block);
+            page = page.saturating_add(4);
+        }
+
+        out.truncate(count as usize * 4);
+        Ok(out)
+    }
+
+    /// Issue an NTAG Type 2 Tag WRITE (`0xA2`): writes one 4-byte page and
+    /// confirms the card acknowledged it.
+    pub fn write_ntag_page(&mut self, page: u8, data: &[u8; 4]) -> Result<(), Pn5180Error> {
+        let cmd = [tag_cmd::WRITE, page, data[0], data[1], data[2], data[3]];
+        let mut ack = [0u8; 1];
+        self.rf_exchange(&cmd, &mut ack)?;
+
+        if ack[0] != NTAG_WRITE_ACK {
+            return Err(Pn5180Error::WriteNotAcked);
+        }
+        Ok(())
+    }
+
+    /// Authenticate a MIFARE Classic sector against `block` using `key`,
+    /// required before `mifare_read_block`/`mifare_write_block` will succeed.
+    pub fn mifare_authenticate(
+        &mut self,
+        block: u8,
+        key_type: MifareKeyType,
+        key: &[u8; 6],
+        uid: &[u8; 4],
+    ) -> Result<(), Pn5180Error> {
+        let key_type_byte = match key_type {
+            MifareKeyType::A => 0x60,
+            MifareKeyType::B => 0x61,
+        };
+
+        let mut cmd = [0u8; 12];
+        cmd[0] = cmd::MIFARE_AUTHENTICATE;
+        cmd[1] = block;
+        cmd[2] = key_type_byte;
+        cmd[3..9].copy_from_slice(key);
+        cmd[9..12].copy_from_slice(&uid[..3]);
+
+        let mut status = [0u8; 1];
+        self.transceive(&cmd, &mut status)?;
+
+        if status[0] != 0x00 {
+            return Err(Pn5180Error::AuthFailed);
+        }
+        Ok(())
+    }
+
+    /// Read one 16-byte MIFARE Classic block. Requires a prior successful
+    /// `mifare_authenticate` for the sector containing `block`.
+    pub fn mifare_read_block(&mut self, block: u8) -> Result<[u8; 16], Pn5180Error> {
+        let mut response = [0u8; 16];
+        self.rf_exchange(&[0x30, block], &mut response)?;
+        Ok(response)
+    }
+
+    /// Write one 16-byte MIFARE Classic block. Requires a prior successful
+    /// `mifare_authenticate` for the sector containing `block`.
+    pub fn mifare_write_block(&mut self, block: u8, data: &[u8; 16]) -> Result<(), Pn5180Error> {
+        let mut ack = [0u8; 1];
+        self.rf_exchange(&[0xA0, block], &mut ack)?;
+        if ack[0] != NTAG_WRITE_ACK {
+            return Err(Pn5180Error::WriteNotAcked);
+        }
+
+        self.rf_exchange(data, &mut ack)?;
+        if ack[0] != NTAG_WRITE_ACK {
+            return Err(Pn5180Error::WriteNotAcked);
+        }
+        Ok(())
+    }
+
+    /// Load the target-mode (listen) RF configuration used for Host Card
+    /// Emulation, mirroring `load_rf_config_iso14443a`'s reader-mode counterpart.
+    pub fn load_rf_config_target_mode(&mut self) -> Result<(), Pn5180Error> {
+        self.transceive(&[cmd::LOAD_RF_CONFIG, 0x80, 0x00], &mut [])
+    }
+
+    /// True once an external reader's field has activated us while in target mode.
+    pub fn field_present(&mut self) -> Result<bool, Pn5180Error> {
+        let status = self.read_register(REG_RF_STATUS)?;
+        Ok(status & 0x01 != 0)
+    }
+
+    /// Receive one command frame sent by an external reader while in listen mode.
+    fn receive_reader_frame(&mut self, buf: &mut [u8]) -> Result<(), Pn5180Error> {
+        self.transceive(&[cmd::READ_DATA, 0x00], buf)
+    }
+
+    /// Reply to the reader with `data` (e.g. the 16 bytes of a requested NTAG page quad).
+    fn transmit_reply(&mut self, data: &[u8]) -> Result<(), Pn5180Error> {
+        let mut send_cmd = Vec::with_capacity(data.len() + 2);
+        send_cmd.push(cmd::SEND_DATA);
+        send_cmd.push(0x00);
+        send_cmd.extend_from_slice(data);
+        self.transceive(&send_cmd, &mut [])
+    }
+
+    /// Answer one reader command while emulating a tag backed by `memory`
+    /// (as built by `emulation::build_emulation_memory`). Returns `true` if a
+    /// reader's field is present and a command was served.
+    pub fn serve_listen_frame(&mut self, memory: &[u8]) -> Result<bool, Pn5180Error> {
+        if !self.field_present()? {
+            return Ok(false);
+        }
+
+        let mut frame = [0u8; 2];
+        self.receive_reader_frame(&mut frame)?;
+
+        match frame[0] {
+            tag_cmd::READ => {
+                // NTAG READ replies with 4 pages (16 bytes); pages past the end read as zeros.
+                let mut response = [0u8; 16];
+                for (i, chunk) in response.chunks_mut(4).enumerate() {
+                    let page = super::emulation::read_emulated_page(memory, frame[1].wrapping_add(i as u8))
+                        .unwrap_or([0u8; 4]);
+                    chunk.copy_from_slice(&page);
+                }
+                self.transmit_reply(&response)?;
+                Ok(true)
+            }
+            tag_cmd::WRITE => {
+                // Emulation is read-only today: acknowledge but discard the write.
+                self.transmit_reply(&[NTAG_WRITE_ACK])?;
+                Ok(true)
+            }
+            _ => Ok(true),
+        }
+    }
+
+    /// Run ISO14443-A activation: REQA, anticollision and SELECT.
+    ///
+    /// Returns `Ok(None)` (instead of `Err(Timeout)`) when no card is in the
+    /// field, since that is the expected steady state while polling.
+    pub fn iso14443a_activate(&mut self) -> Result<Option<Iso14443aCard>, Pn5180Error> {
+        self.load_rf_config_iso14443a()?;
+
+        // REQA (short frame, 7 bits) -> 2-byte ATQA.
+        let mut atqa = [0u8; 2];
+        match self.rf_exchange(&[0x26], &mut atqa) {
+            Ok(()) => {}
+            Err(Pn5180Error::Timeout) => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        if atqa == [0x00, 0x00] {
+            return Ok(None);
+        }
+
+        // Anticollision cascade level 1 (single-size UID assumed; cards that
+        // reply with cascade tag 0x88 use a 7-byte UID spanning two levels).
+        let mut uid_resp = [0u8; 5]; // 4 UID bytes + BCC
+        self.rf_exchange(&[0x93, 0x20], &mut uid_resp)?;
+
+        let (uid, uid_len) = if uid_resp[0] == 0x88 {
+            // Cascade tag: this level only carries 3 real UID bytes; a real
+            // driver would run a second anticollision level for bytes 4-7.
+            let mut uid = [0u8; 10];
+            uid[..3].copy_from_slice(&uid_resp[1..4]);
+            (uid, 7u8)
+        } else {
+            let mut uid = [0u8; 10];
+            uid[..4].copy_from_slice(&uid_resp[..4]);
+            (uid, 4u8)
+        };
+
+        // SELECT with the UID we just collected -> 1-byte SAK.
+        let mut select_cmd = [0u8; 7];
+        select_cmd[0] = 0x93;
+        select_cmd[1] = 0x70;
+        select_cmd[2..6].copy_from_slice(&uid_resp[..4]);
+        select_cmd[6] = uid_resp[4];
+        let mut sak = [0u8; 1];
+        self.rf_exchange(&select_cmd, &mut sak)?;
+
+        Ok(Some(Iso14443aCard {
+            uid,
+            uid_len,
+            atqa,
+            sak: sak[0],
+        }))
+    }
+
+    /// Exercise the SPI link without requiring a card: write then read back
+    /// a scratch register, verifying the bus can both clock data out and
+    /// sample data in.
+    pub fn spi_diagnostic_test(&mut self) -> Result<(), Pn5180Error> {
+        const SCRATCH_REGISTER: u8 = 0x3F;
+        const PATTERN: u32 = 0xA5A5_5A5A;
+
+        self.write_register(SCRATCH_REGISTER, PATTERN)?;
+        let readback = self.read_register(SCRATCH_REGISTER)?;
+
+        if readback != PATTERN {
+            return Err(Pn5180Error::RegisterMismatch);
+        }
+
+        Ok(())
+    }
+
+    /// Sample the AGC (automatic gain control) value from the RF status
+    /// register. Meant to be read with the RF field on; a low value with no
+    /// card present can indicate a weak or miscalibrated antenna.
+    pub fn read_agc_value(&mut self) -> Result<u8, Pn5180Error> {
+        let status = self.read_register(REG_RF_STATUS)?;
+        Ok(((status >> 16) & 0xFF) as u8)
+    }
+
+    /// Factory/field self-test: exercises the PN5180 without requiring a
+    /// card present. Confirms the firmware version readback, runs a
+    /// register write/read loopback sanity check, and samples the AGC value
+    /// with the field on so a weak antenna shows up even though
+    /// `nfc_is_initialized` would still report true.
+    pub fn run_self_test(&mut self) -> Pn5180SelfTestResult {
+        let mut result = Pn5180SelfTestResult::default();
+
+        if let Ok(version) = self.get_firmware_version() {
+            result.firmware_ok = true;
+            result.firmware_version = version;
+        }
+
+        result.register_loopback_ok = self.spi_diagnostic_test().is_ok();
+
+        if self.rf_on().is_ok() {
+            if let Ok(agc) = self.read_agc_value() {
+                result.agc_ok = true;
+                result.agc_value = agc;
+            }
+            let _ = self.rf_off();
+        }
+
+        result
+    }
+}
+
+/// Bring up a PN5180 driver: pulse RST if one is wired (no reset is sent
+/// otherwise), wait for power-on, then confirm the link is alive by reading
+/// and caching the firmware version.
+pub fn init_pn5180<'a, SPI>(
+    spi: SPI,
+    nss: PinDriver<'a, AnyOutputPin, Output>,
+    busy: Option<PinDriver<'a, Gpio2, Input>>,
+    rst: Option<PinDriver<'a, AnyOutputPin, Output>>,
+    state: &mut Pn5180State,
+) -> Result<Pn5180Driver<'a, SPI>, Pn5180Error>
+where
+    SPI: SpiDevice,
+{
+    let mut driver = Pn5180Driver { spi, nss, busy, rst };
+
+    if let Some(ref mut rst) = driver.rst {
+        rst.set_low().map_err(|_| Pn5180Error::Gpio)?;
+        FreeRtos::delay_ms(10);
+        rst.set_high().map_err(|_| Pn5180Error::Gpio)?;
+    }
+    FreeRtos::delay_ms(50); // Power-on settle time per datasheet.
+
+    let version = driver.get_firmware_version()?;
+    state.firmware_version = version;
+    state.initialized = true;
+
+    Ok(driver)
+}
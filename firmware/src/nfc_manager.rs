@@ -6,13 +6,47 @@
 use esp_idf_hal::gpio::{Input, Output, PinDriver};
 use embedded_hal::spi::SpiDevice;
 use log::{info, warn};
+use std::ffi::{CStr, c_char};
 use std::sync::Mutex;
 
+use crate::nfc::ndef::{self, NdefError};
 use crate::nfc::pn5180::{self, Iso14443aCard, Pn5180Driver, Pn5180Error, Pn5180State};
 
 /// Global NFC state protected by mutex
 static NFC_STATE: Mutex<Option<NfcManagerState>> = Mutex::new(None);
 
+/// Consecutive `Pn5180Error`s (other than the expected no-card `Timeout`)
+/// `poll_reader` will tolerate before dropping into `NfcFsmState::Error` and
+/// cycling the RF field back through `Reset`.
+const MAX_CONSECUTIVE_FAULTS: u8 = 3;
+
+/// Maximum number of distinct tags kept in `NfcManagerState::tracked_tags`.
+const MAX_TRACKED_TAGS: usize = 4;
+
+/// Poll ticks a tracked tag is allowed to go unseen before it's purged
+/// (debounces a momentary read miss from a tag that's actually still there).
+const TAG_EXPIRY_TICKS: u32 = 5;
+
+/// NCI-flavored poll state machine. Each `poll_nfc` tick advances the state
+/// based on the previous tick's result, instead of a bare poll counter, so
+/// RF-field management and fault recovery are explicit and the UI can show
+/// "searching" vs "reading" vs "error" instead of just a card-present flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NfcFsmState {
+    /// Field is being (re)initialized after startup or an error streak.
+    Reset,
+    /// RF field is off; about to be switched on before discovery resumes.
+    FieldOff,
+    /// RF field is on, no card activated yet.
+    Discovery,
+    /// A card was just activated this tick.
+    CardActivated,
+    /// A card remains activated from a previous tick.
+    Reading,
+    /// Too many consecutive faults; backing off before the next `Reset`.
+    Error,
+}
+
 /// NFC manager state (without driver - driver is type-erased)
 struct NfcManagerState {
     /// PN5180 state
@@ -25,6 +59,27 @@ struct NfcManagerState {
     card_present: bool,
     /// Poll counter
     poll_count: u32,
+    /// Current poll state machine state
+    fsm: NfcFsmState,
+    /// Consecutive poll faults (excludes the expected no-card `Timeout`)
+    consecutive_faults: u8,
+    /// Poll ticks elapsed, used for tag expiry in `tracked_tags`
+    tick: u32,
+    /// Distinct tags seen recently, for multi-tag disambiguation
+    tracked_tags: Vec<TrackedTag>,
+    /// NDEF records read from the last card via `nfc_read_ndef`
+    ndef_records: Vec<ndef::NdefRecord>,
+    /// Whether tag emulation (Host Card Emulation) is enabled
+    emulation_enabled: bool,
+    /// Emulated tag memory (TLV-wrapped NDEF), set via `nfc_set_emulation_ndef`
+    emulation_memory: Vec<u8>,
+}
+
+/// One tag tracked across polls for the multi-tag list, with last-seen
+/// bookkeeping so a stale entry can be purged after `TAG_EXPIRY_TICKS`.
+struct TrackedTag {
+    card: CardInfo,
+    last_seen_tick: u32,
 }
 
 /// Card information for FFI
@@ -71,11 +126,28 @@ pub struct NfcStatus {
     pub initialized: bool,
     pub rf_on: bool,
     pub card_present: bool,
+    /// Poll state machine: 0=Reset, 1=FieldOff, 2=Discovery, 3=CardActivated,
+    /// 4=Reading, 5=Error. Lets the UI show "searching" vs "reading" vs "error".
+    pub state: u8,
     pub firmware_major: u8,
     pub firmware_minor: u8,
     pub firmware_patch: u8,
 }
 
+impl NfcFsmState {
+    /// Numeric code for the FFI-facing `NfcStatus::state` field.
+    fn code(self) -> u8 {
+        match self {
+            NfcFsmState::Reset => 0,
+            NfcFsmState::FieldOff => 1,
+            NfcFsmState::Discovery => 2,
+            NfcFsmState::CardActivated => 3,
+            NfcFsmState::Reading => 4,
+            NfcFsmState::Error => 5,
+        }
+    }
+}
+
 /// Initialize the NFC manager (called after driver init)
 pub fn init_nfc_manager(state: Pn5180State) {
     let mut guard = NFC_STATE.lock().unwrap();
@@ -85,6 +157,13 @@ pub fn init_nfc_manager(state: Pn5180State) {
         rf_on: false,
         card_present: false,
         poll_count: 0,
+        fsm: NfcFsmState::Reset,
+        consecutive_faults: 0,
+        tick: 0,
+        tracked_tags: Vec::new(),
+        ndef_records: Vec::new(),
+        emulation_enabled: false,
+        emulation_memory: Vec::new(),
     });
     info!("NFC manager initialized");
 }
@@ -102,6 +181,24 @@ struct NfcDriverHolder {
     rf_on_fn: Box<dyn FnMut() -> Result<(), Pn5180Error>>,
     /// Function pointer to turn RF off
     rf_off_fn: Box<dyn FnMut() -> Result<(), Pn5180Error>>,
+    /// Function pointer to read and decode the NDEF message off the activated card
+    read_ndef_fn: Box<dyn FnMut() -> Result<Vec<ndef::NdefRecord>, NdefError>>,
+    /// Function pointer to read consecutive NTAG pages (start, count)
+    read_pages_fn: Box<dyn FnMut(u8, u8) -> Result<Vec<u8>, Pn5180Error>>,
+    /// Function pointer to write one NTAG page (page, data)
+    write_page_fn: Box<dyn FnMut(u8, [u8; 4]) -> Result<(), Pn5180Error>>,
+    /// Function pointer to authenticate + read one MIFARE Classic block (block, uid)
+    mifare_read_fn: Box<dyn FnMut(u8, [u8; 4]) -> Result<[u8; 16], Pn5180Error>>,
+    /// Function pointer to authenticate + write one MIFARE Classic block (block, uid, data)
+    mifare_write_fn: Box<dyn FnMut(u8, [u8; 4], [u8; 16]) -> Result<(), Pn5180Error>>,
+    /// Function pointer to switch the PN5180 into target/listen mode for emulation
+    enable_emulation_fn: Box<dyn FnMut() -> Result<(), Pn5180Error>>,
+    /// Function pointer to return the PN5180 to reader mode
+    disable_emulation_fn: Box<dyn FnMut() -> Result<(), Pn5180Error>>,
+    /// Function pointer to serve one reader command against emulated memory
+    serve_listen_fn: Box<dyn FnMut(&[u8]) -> Result<bool, Pn5180Error>>,
+    /// Function pointer to run the antenna/register self-test
+    self_test_fn: Box<dyn FnMut() -> pn5180::Pn5180SelfTestResult>,
 }
 
 /// Initialize NFC driver holder with the actual driver
@@ -133,11 +230,76 @@ pub fn init_nfc_driver<'a, SPI>(
         driver.rf_off()
     });
 
+    let driver_ptr4 = driver_ptr;
+    let read_ndef_fn = Box::new(move || {
+        let driver = unsafe { &mut *driver_ptr4 };
+        ndef::read_ndef(driver)
+    });
+
+    let driver_ptr5 = driver_ptr;
+    let read_pages_fn = Box::new(move |start: u8, count: u8| {
+        let driver = unsafe { &mut *driver_ptr5 };
+        driver.read_ntag_page_range(start, count)
+    });
+
+    let driver_ptr6 = driver_ptr;
+    let write_page_fn = Box::new(move |page: u8, data: [u8; 4]| {
+        let driver = unsafe { &mut *driver_ptr6 };
+        driver.write_ntag_page(page, &data)
+    });
+
+    let driver_ptr7 = driver_ptr;
+    let mifare_read_fn = Box::new(move |block: u8, uid: [u8; 4]| {
+        let driver = unsafe { &mut *driver_ptr7 };
+        driver.mifare_authenticate(block, pn5180::MifareKeyType::A, &pn5180::MIFARE_NDEF_KEY_A, &uid)?;
+        driver.mifare_read_block(block)
+    });
+
+    let driver_ptr8 = driver_ptr;
+    let mifare_write_fn = Box::new(move |block: u8, uid: [u8; 4], data: [u8; 16]| {
+        let driver = unsafe { &mut *driver_ptr8 };
+        driver.mifare_authenticate(block, pn5180::MifareKeyType::A, &pn5180::MIFARE_NDEF_KEY_A, &uid)?;
+        driver.mifare_write_block(block, &data)
+    });
+
+    let driver_ptr9 = driver_ptr;
+    let enable_emulation_fn = Box::new(move || {
+        let driver = unsafe { &mut *driver_ptr9 };
+        crate::nfc::emulation::enable_emulation(driver)
+    });
+
+    let driver_ptr10 = driver_ptr;
+    let disable_emulation_fn = Box::new(move || {
+        let driver = unsafe { &mut *driver_ptr10 };
+        crate::nfc::emulation::disable_emulation(driver)
+    });
+
+    let driver_ptr11 = driver_ptr;
+    let serve_listen_fn = Box::new(move |memory: &[u8]| {
+        let driver = unsafe { &mut *driver_ptr11 };
+        driver.serve_listen_frame(memory)
+    });
+
+    let driver_ptr12 = driver_ptr;
+    let self_test_fn = Box::new(move || {
+        let driver = unsafe { &mut *driver_ptr12 };
+        driver.run_self_test()
+    });
+
     let mut guard = NFC_DRIVER.lock().unwrap();
     *guard = Some(NfcDriverHolder {
         poll_fn,
         rf_on_fn,
         rf_off_fn,
+        read_ndef_fn,
+        read_pages_fn,
+        write_page_fn,
+        mifare_read_fn,
+        mifare_write_fn,
+        enable_emulation_fn,
+        disable_emulation_fn,
+        serve_listen_fn,
+        self_test_fn,
     });
 
     info!("NFC driver holder initialized");
@@ -156,59 +318,434 @@ pub fn poll_nfc() {
             return;
         }
 
-        // Ensure RF is on
-        if !state.rf_on {
-            match (driver.rf_on_fn)() {
-                Ok(()) => {
-                    state.rf_on = true;
-                    info!("NFC RF field enabled");
-                }
-                Err(e) => {
-                    warn!("Failed to enable RF field: {:?}", e);
-                    return;
-                }
-            }
+        if state.emulation_enabled {
+            poll_emulation(driver, state);
+        } else {
+            poll_reader(driver, state);
         }
+    }
+}
 
-        // Try to detect a card
-        match (driver.poll_fn)() {
-            Ok(Some(card)) => {
-                if !state.card_present {
-                    info!("NFC card detected! ATQA: {:02X}{:02X}, SAK: {:02X}",
-                          card.atqa[0], card.atqa[1], card.sak);
-                    if card.uid_len > 0 {
-                        let uid_str: String = card.uid[..card.uid_len as usize]
-                            .iter()
-                            .map(|b| format!("{:02X}", b))
-                            .collect::<Vec<_>>()
-                            .join(":");
-                        info!("  UID: {}", uid_str);
-                    }
-                }
-                state.card_present = true;
-                state.last_card = Some(CardInfo::from(&card));
+/// Record a fault from a poll/RF-control attempt and drop into `Error` once
+/// `MAX_CONSECUTIVE_FAULTS` have happened back to back.
+fn record_fault(state: &mut NfcManagerState) {
+    state.consecutive_faults = state.consecutive_faults.saturating_add(1);
+    if state.consecutive_faults >= MAX_CONSECUTIVE_FAULTS {
+        warn!("NFC: {} consecutive faults, resetting RF field", state.consecutive_faults);
+        state.rf_on = false;
+        state.card_present = false;
+        state.fsm = NfcFsmState::Error;
+    }
+}
+
+/// Refresh (or add) `card` in the tracked-tag list, keyed by UID. Evicts the
+/// least-recently-seen entry to make room once `MAX_TRACKED_TAGS` is reached,
+/// mirroring the ESPHome PN71xx `purge_old_tags_` bookkeeping pattern.
+fn upsert_tracked_tag(state: &mut NfcManagerState, card: CardInfo) {
+    let tick = state.tick;
+    let uid_len = card.uid_len as usize;
+
+    if let Some(existing) = state
+        .tracked_tags
+        .iter_mut()
+        .find(|t| t.card.uid_len as usize == uid_len && t.card.uid[..uid_len] == card.uid[..uid_len])
+    {
+        existing.card = card;
+        existing.last_seen_tick = tick;
+        return;
+    }
+
+    if state.tracked_tags.len() >= MAX_TRACKED_TAGS {
+        if let Some((oldest_idx, _)) = state
+            .tracked_tags
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, t)| t.last_seen_tick)
+        {
+            state.tracked_tags.remove(oldest_idx);
+        }
+    }
+
+    state.tracked_tags.push(TrackedTag { card, last_seen_tick: tick });
+}
+
+/// Drop tags that haven't been seen for `TAG_EXPIRY_TICKS` poll ticks.
+fn purge_expired_tags(state: &mut NfcManagerState) {
+    let tick = state.tick;
+    state
+        .tracked_tags
+        .retain(|t| tick.wrapping_sub(t.last_seen_tick) <= TAG_EXPIRY_TICKS);
+}
+
+/// Normal reader-mode poll cycle: an explicit state machine (`Reset` ->
+/// `FieldOff` -> `Discovery` -> `CardActivated`/`Reading`, with any state
+/// falling back to `Error` -> `Reset` after repeated faults) in place of the
+/// old poll-counter-driven `rf_on`/`card_present` flags. This lets the driver
+/// back off and retry after a run of `Pn5180Error`s. `card_present` follows
+/// the latest poll and clears on the first miss; only `tracked_tags` rides
+/// out misses (for `TAG_EXPIRY_TICKS`). The RF field stays on from
+/// `FieldOff` onwards and is only switched off again by `Reset`.
+fn poll_reader(driver: &mut NfcDriverHolder, state: &mut NfcManagerState) {
+    state.tick = state.tick.wrapping_add(1);
+    purge_expired_tags(state);
+
+    match state.fsm {
+        NfcFsmState::Reset => {
+            if let Err(e) = (driver.rf_off_fn)() {
+                warn!("NFC: RF-off during reset failed: {:?}", e);
             }
-            Ok(None) => {
-                if state.card_present {
-                    info!("NFC card removed");
-                }
-                state.card_present = false;
+            state.rf_on = false;
+            state.card_present = false;
+            state.consecutive_faults = 0;
+            state.fsm = NfcFsmState::FieldOff;
+        }
+        NfcFsmState::FieldOff => match (driver.rf_on_fn)() {
+            Ok(()) => {
+                state.rf_on = true;
+                state.consecutive_faults = 0;
+                state.fsm = NfcFsmState::Discovery;
+                info!("NFC RF field enabled");
             }
             Err(e) => {
-                // Timeout is normal when no card present
-                if !matches!(e, Pn5180Error::Timeout) {
+                warn!("Failed to enable RF field: {:?}", e);
+                record_fault(state);
+            }
+        },
+        NfcFsmState::Error => {
+            // Give the field a tick to settle before trying again.
+            state.fsm = NfcFsmState::Reset;
+        }
+        NfcFsmState::Discovery | NfcFsmState::CardActivated | NfcFsmState::Reading => {
+            match (driver.poll_fn)() {
+                Ok(Some(card)) => {
+                    if !state.card_present {
+                        info!("NFC card detected! ATQA: {:02X}{:02X}, SAK: {:02X}",
+                              card.atqa[0], card.atqa[1], card.sak);
+                        if card.uid_len > 0 {
+                            let uid_str: String = card.uid[..card.uid_len as usize]
+                                .iter()
+                                .map(|b| format!("{:02X}", b))
+                                .collect::<Vec<_>>()
+                                .join(":");
+                            info!("  UID: {}", uid_str);
+                        }
+                    }
+                    state.card_present = true;
+                    let info = CardInfo::from(&card);
+                    upsert_tracked_tag(state, info.clone());
+                    state.last_card = Some(info);
+                    state.consecutive_faults = 0;
+                    state.fsm = if state.fsm == NfcFsmState::Discovery {
+                        NfcFsmState::CardActivated
+                    } else {
+                        NfcFsmState::Reading
+                    };
+                }
+                Ok(None) => {
+                    if state.card_present {
+                        info!("NFC card removed");
+                    }
+                    state.card_present = false;
+                    state.fsm = NfcFsmState::Discovery;
+                }
+                Err(Pn5180Error::Timeout) => {
+                    // Timeout is normal when no card present; stay in Discovery.
+                    state.card_present = false;
+                    state.fsm = NfcFsmState::Discovery;
+                }
+                Err(e) => {
                     warn!("NFC poll error: {:?}", e);
+                    state.card_present = false;
+                    record_fault(state);
                 }
-                state.card_present = false;
             }
         }
     }
 }
 
+/// Emulation-mode poll cycle: alternate between presenting ourselves as a tag
+/// (listen mode, so a phone can read us) and briefly reverting to reader
+/// mode (so we can still notice real spool tags while emulation is on).
+fn poll_emulation(driver: &mut NfcDriverHolder, state: &mut NfcManagerState) {
+    let listen_turn = (state.poll_count / 10) % 2 == 0;
+
+    if listen_turn {
+        if let Err(e) = (driver.enable_emulation_fn)() {
+            warn!("Failed to enter tag-emulation listen mode: {:?}", e);
+            return;
+        }
+        match (driver.serve_listen_fn)(&state.emulation_memory) {
+            Ok(true) => info!("Served an NDEF read to an external reader"),
+            Ok(false) => {}
+            Err(e) => warn!("Tag-emulation serve error: {:?}", e),
+        }
+    } else {
+        if let Err(e) = (driver.disable_emulation_fn)() {
+            warn!("Failed to leave tag-emulation listen mode: {:?}", e);
+        }
+        poll_reader(driver, state);
+    }
+}
+
+/// Read and decode the NDEF message off the currently-present card.
+/// Caches the decoded records so `nfc_get_ndef_record` can retrieve them.
+/// Returns true on success, false if no card is present or the read/parse failed.
+pub fn read_ndef() -> bool {
+    let mut driver_guard = NFC_DRIVER.lock().unwrap();
+    let mut state_guard = NFC_STATE.lock().unwrap();
+
+    let (Some(ref mut driver), Some(ref mut state)) = (&mut *driver_guard, &mut *state_guard) else {
+        return false;
+    };
+
+    if !state.card_present {
+        warn!("read_ndef: no card present");
+        return false;
+    }
+
+    match (driver.read_ndef_fn)() {
+        Ok(records) => {
+            info!("Read {} NDEF record(s)", records.len());
+            state.ndef_records = records;
+            true
+        }
+        Err(e) => {
+            warn!("NDEF read failed: {:?}", e);
+            state.ndef_records.clear();
+            false
+        }
+    }
+}
+
+/// Build the emulated tag's NDEF content (a URI record plus an external
+/// record carrying `payload`, e.g. the current weight/material) and cache it
+/// for `poll_nfc` to serve once emulation is enabled.
+pub fn set_emulation_ndef(uri: &str, external_type: &str, payload: &[u8]) {
+    let mut guard = NFC_STATE.lock().unwrap();
+    if let Some(ref mut state) = *guard {
+        state.emulation_memory = crate::nfc::emulation::build_emulation_memory(uri, external_type, payload);
+        info!("Emulation NDEF set ({} bytes)", state.emulation_memory.len());
+    }
+}
+
+/// Enable or disable Host Card Emulation (tag-emulation) mode.
+pub fn set_emulation_enabled(enabled: bool) {
+    let mut state_guard = NFC_STATE.lock().unwrap();
+    if let Some(ref mut state) = *state_guard {
+        state.emulation_enabled = enabled;
+    }
+    drop(state_guard);
+
+    if !enabled {
+        let mut driver_guard = NFC_DRIVER.lock().unwrap();
+        if let Some(ref mut driver) = *driver_guard {
+            if let Err(e) = (driver.disable_emulation_fn)() {
+                warn!("Failed to disable tag emulation: {:?}", e);
+            }
+        }
+    }
+
+    info!("Tag emulation {}", if enabled { "enabled" } else { "disabled" });
+}
+
+/// FFI-facing decoded NDEF record. Fixed-size buffers since this crosses the C boundary.
+#[repr(C)]
+#[derive(Debug, Clone)]
+pub struct NdefRecord {
+    /// Type Name Format of the record.
+    pub tnf: u8,
+    /// Record type (e.g. "U", "T"), truncated to fit.
+    pub record_type: [u8; 8],
+    /// Valid bytes in `record_type`.
+    pub record_type_len: u8,
+    /// Decoded display text for URI/Text records, empty otherwise.
+    pub text: [u8; 128],
+    /// Valid bytes in `text`.
+    pub text_len: u16,
+}
+
+impl From<&ndef::NdefRecord> for NdefRecord {
+    fn from(record: &ndef::NdefRecord) -> Self {
+        let mut record_type = [0u8; 8];
+        let type_bytes = record.record_type.as_bytes();
+        let type_len = type_bytes.len().min(record_type.len());
+        record_type[..type_len].copy_from_slice(&type_bytes[..type_len]);
+
+        let mut text = [0u8; 128];
+        let text_len = record
+            .text
+            .as_deref()
+            .map(|t| {
+                let bytes = t.as_bytes();
+                let len = bytes.len().min(text.len());
+                text[..len].copy_from_slice(&bytes[..len]);
+                len
+            })
+            .unwrap_or(0);
+
+        NdefRecord {
+            tnf: record.tnf,
+            record_type,
+            record_type_len: type_len as u8,
+            text,
+            text_len: text_len as u16,
+        }
+    }
+}
+
+/// Result codes for block-level NTAG/MIFARE access, surfaced over FFI so the
+/// scale/NFC fusion logic can decide whether to fall back to UID-only tracking.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NfcDataError {
+    Ok = 0,
+    NoCard = 1,
+    CardTypeMismatch = 2,
+    AuthFailed = 3,
+    Timeout = 4,
+    WriteFailed = 5,
+    Other = 6,
+}
+
+impl From<Pn5180Error> for NfcDataError {
+    fn from(e: Pn5180Error) -> Self {
+        match e {
+            Pn5180Error::Timeout => NfcDataError::Timeout,
+            Pn5180Error::AuthFailed => NfcDataError::AuthFailed,
+            Pn5180Error::WriteNotAcked => NfcDataError::WriteFailed,
+            _ => NfcDataError::Other,
+        }
+    }
+}
+
+/// Read `count` consecutive 4-byte NTAG pages starting at `start`.
+pub fn read_pages(start: u8, count: u8) -> Result<Vec<u8>, NfcDataError> {
+    let mut driver_guard = NFC_DRIVER.lock().unwrap();
+    let state_guard = NFC_STATE.lock().unwrap();
+
+    let (Some(ref mut driver), Some(ref state)) = (&mut *driver_guard, &*state_guard) else {
+        return Err(NfcDataError::NoCard);
+    };
+    if !state.card_present {
+        return Err(NfcDataError::NoCard);
+    }
+
+    (driver.read_pages_fn)(start, count).map_err(NfcDataError::from)
+}
+
+/// Write one 4-byte NTAG page.
+pub fn write_page(page: u8, data: [u8; 4]) -> Result<(), NfcDataError> {
+    let mut driver_guard = NFC_DRIVER.lock().unwrap();
+    let state_guard = NFC_STATE.lock().unwrap();
+
+    let (Some(ref mut driver), Some(ref state)) = (&mut *driver_guard, &*state_guard) else {
+        return Err(NfcDataError::NoCard);
+    };
+    if !state.card_present {
+        return Err(NfcDataError::NoCard);
+    }
+
+    (driver.write_page_fn)(page, data).map_err(NfcDataError::from)
+}
+
+/// Authenticate (with the standard NDEF key A) and read one MIFARE Classic block.
+pub fn mifare_read_block(block: u8) -> Result<[u8; 16], NfcDataError> {
+    let mut driver_guard = NFC_DRIVER.lock().unwrap();
+    let state_guard = NFC_STATE.lock().unwrap();
+
+    let (Some(ref mut driver), Some(ref state)) = (&mut *driver_guard, &*state_guard) else {
+        return Err(NfcDataError::NoCard);
+    };
+    if !state.card_present {
+        return Err(NfcDataError::NoCard);
+    }
+    let Some(ref card) = state.last_card else {
+        return Err(NfcDataError::NoCard);
+    };
+    if card.card_type != 2 && card.card_type != 3 {
+        return Err(NfcDataError::CardTypeMismatch);
+    }
+
+    let mut uid = [0u8; 4];
+    uid.copy_from_slice(&card.uid[..4]);
+    (driver.mifare_read_fn)(block, uid).map_err(NfcDataError::from)
+}
+
+/// Authenticate (with the standard NDEF key A) and write one MIFARE Classic block.
+pub fn mifare_write_block(block: u8, data: [u8; 16]) -> Result<(), NfcDataError> {
+    let mut driver_guard = NFC_DRIVER.lock().unwrap();
+    let state_guard = NFC_STATE.lock().unwrap();
+
+    let (Some(ref mut driver), Some(ref state)) = (&mut *driver_guard, &*state_guard) else {
+        return Err(NfcDataError::NoCard);
+    };
+    if !state.card_present {
+        return Err(NfcDataError::NoCard);
+    }
+    let Some(ref card) = state.last_card else {
+        return Err(NfcDataError::NoCard);
+    };
+    if card.card_type != 2 && card.card_type != 3 {
+        return Err(NfcDataError::CardTypeMismatch);
+    }
+
+    let mut uid = [0u8; 4];
+    uid.copy_from_slice(&card.uid[..4]);
+    (driver.mifare_write_fn)(block, uid, data).map_err(NfcDataError::from)
+}
+
+/// Run the antenna/register self-test. Does not require a card present, and
+/// temporarily drives the RF field, so it should not be called while a
+/// normal poll cycle is relying on field state (e.g. mid-emulation).
+pub fn run_self_test() -> Option<pn5180::Pn5180SelfTestResult> {
+    let mut driver_guard = NFC_DRIVER.lock().unwrap();
+    let driver = driver_guard.as_mut()?;
+    Some((driver.self_test_fn)())
+}
+
 // =============================================================================
 // C-callable FFI functions
 // =============================================================================
 
+/// Antenna/register self-test results for C code. Lets a factory or field
+/// test distinguish "not initialized" from "initialized, but the antenna is
+/// weak or miscalibrated" (a low `agc_value` with `agc_ok` true).
+#[repr(C)]
+pub struct NfcDiagnostics {
+    pub firmware_ok: bool,
+    pub firmware_major: u8,
+    pub firmware_minor: u8,
+    pub firmware_patch: u8,
+    pub register_loopback_ok: bool,
+    pub agc_ok: bool,
+    pub agc_value: u8,
+}
+
+/// Run the PN5180 self-test (firmware readback, register loopback, AGC
+/// sample) and write the results to `out`. Returns false if the driver isn't
+/// initialized yet.
+#[no_mangle]
+pub extern "C" fn nfc_run_self_test(out: *mut NfcDiagnostics) -> bool {
+    if out.is_null() {
+        return false;
+    }
+
+    let Some(result) = run_self_test() else {
+        return false;
+    };
+
+    unsafe {
+        (*out).firmware_ok = result.firmware_ok;
+        (*out).firmware_major = result.firmware_version.0;
+        (*out).firmware_minor = result.firmware_version.1;
+        (*out).firmware_patch = result.firmware_version.2;
+        (*out).register_loopback_ok = result.register_loopback_ok;
+        (*out).agc_ok = result.agc_ok;
+        (*out).agc_value = result.agc_value;
+    }
+    true
+}
+
 /// Get NFC status
 #[no_mangle]
 pub extern "C" fn nfc_get_status(status: *mut NfcStatus) {
@@ -223,6 +760,7 @@ pub extern "C" fn nfc_get_status(status: *mut NfcStatus) {
         status.initialized = manager.state.initialized;
         status.rf_on = manager.rf_on;
         status.card_present = manager.card_present;
+        status.state = manager.fsm.code();
         status.firmware_major = manager.state.firmware_version.0;
         status.firmware_minor = manager.state.firmware_version.1;
         status.firmware_patch = manager.state.firmware_version.2;
@@ -230,6 +768,7 @@ pub extern "C" fn nfc_get_status(status: *mut NfcStatus) {
         status.initialized = false;
         status.rf_on = false;
         status.card_present = false;
+        status.state = NfcFsmState::Reset.code();
         status.firmware_major = 0;
         status.firmware_minor = 0;
         status.firmware_patch = 0;
@@ -278,6 +817,37 @@ pub extern "C" fn nfc_get_card_info(info: *mut CardInfo) -> bool {
     false
 }
 
+/// Get the number of distinct tags currently tracked (seen within the last
+/// `TAG_EXPIRY_TICKS` poll ticks), for disambiguating multiple spools in the field.
+#[no_mangle]
+pub extern "C" fn nfc_get_tag_count() -> usize {
+    let guard = NFC_STATE.lock().unwrap();
+    guard.as_ref().map(|m| m.tracked_tags.len()).unwrap_or(0)
+}
+
+/// Get a tracked tag by index (0..`nfc_get_tag_count()`). Returns true if `idx` was in range.
+#[no_mangle]
+pub extern "C" fn nfc_get_tag(idx: usize, out: *mut CardInfo) -> bool {
+    if out.is_null() {
+        return false;
+    }
+
+    let guard = NFC_STATE.lock().unwrap();
+    let Some(ref manager) = *guard else {
+        return false;
+    };
+
+    match manager.tracked_tags.get(idx) {
+        Some(tag) => {
+            unsafe {
+                *out = tag.card.clone();
+            }
+            true
+        }
+        None => false,
+    }
+}
+
 /// Get card UID as hex string (returns length, 0 if no card)
 #[no_mangle]
 pub extern "C" fn nfc_get_uid_hex(buf: *mut u8, buf_len: usize) -> usize {
@@ -314,6 +884,149 @@ pub extern "C" fn nfc_get_uid_hex(buf: *mut u8, buf_len: usize) -> usize {
     0
 }
 
+/// Read the NDEF message off the currently-present card and cache it.
+/// Returns true on success, false if no card is present or parsing failed.
+#[no_mangle]
+pub extern "C" fn nfc_read_ndef() -> bool {
+    read_ndef()
+}
+
+/// Get the number of NDEF records cached by the last `nfc_read_ndef` call.
+#[no_mangle]
+pub extern "C" fn nfc_get_ndef_record_count() -> usize {
+    let guard = NFC_STATE.lock().unwrap();
+    guard.as_ref().map(|m| m.ndef_records.len()).unwrap_or(0)
+}
+
+/// Get a cached NDEF record by index. Returns true if `idx` was in range.
+#[no_mangle]
+pub extern "C" fn nfc_get_ndef_record(idx: usize, out: *mut NdefRecord) -> bool {
+    if out.is_null() {
+        return false;
+    }
+
+    let guard = NFC_STATE.lock().unwrap();
+    let Some(ref manager) = *guard else {
+        return false;
+    };
+
+    match manager.ndef_records.get(idx) {
+        Some(record) => {
+            unsafe {
+                *out = NdefRecord::from(record);
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Read `count` consecutive 4-byte NTAG pages starting at `start` into `buf`
+/// (which must be at least `count * 4` bytes). Returns `NfcDataError::Ok` on success.
+#[no_mangle]
+pub extern "C" fn nfc_read_pages(start: u8, count: u8, buf: *mut u8) -> NfcDataError {
+    if buf.is_null() {
+        return NfcDataError::Other;
+    }
+
+    match read_pages(start, count) {
+        Ok(data) => {
+            unsafe {
+                std::ptr::copy_nonoverlapping(data.as_ptr(), buf, data.len());
+            }
+            NfcDataError::Ok
+        }
+        Err(e) => e,
+    }
+}
+
+/// Write one 4-byte NTAG page from `bytes`. Returns `NfcDataError::Ok` on success.
+#[no_mangle]
+pub extern "C" fn nfc_write_page(page: u8, bytes: *const u8) -> NfcDataError {
+    if bytes.is_null() {
+        return NfcDataError::Other;
+    }
+
+    let mut data = [0u8; 4];
+    unsafe {
+        std::ptr::copy_nonoverlapping(bytes, data.as_mut_ptr(), 4);
+    }
+    match write_page(page, data) {
+        Ok(()) => NfcDataError::Ok,
+        Err(e) => e,
+    }
+}
+
+/// Authenticate and read one MIFARE Classic block into `buf` (16 bytes).
+#[no_mangle]
+pub extern "C" fn nfc_mifare_read_block(block: u8, buf: *mut u8) -> NfcDataError {
+    if buf.is_null() {
+        return NfcDataError::Other;
+    }
+
+    match mifare_read_block(block) {
+        Ok(data) => {
+            unsafe {
+                std::ptr::copy_nonoverlapping(data.as_ptr(), buf, data.len());
+            }
+            NfcDataError::Ok
+        }
+        Err(e) => e,
+    }
+}
+
+/// Authenticate and write one MIFARE Classic block from `bytes` (16 bytes).
+#[no_mangle]
+pub extern "C" fn nfc_mifare_write_block(block: u8, bytes: *const u8) -> NfcDataError {
+    if bytes.is_null() {
+        return NfcDataError::Other;
+    }
+
+    let mut data = [0u8; 16];
+    unsafe {
+        std::ptr::copy_nonoverlapping(bytes, data.as_mut_ptr(), 16);
+    }
+    match mifare_write_block(block, data) {
+        Ok(()) => NfcDataError::Ok,
+        Err(e) => e,
+    }
+}
+
+/// Set the NDEF content the device presents when tag emulation is enabled:
+/// a URI record plus an external-type record carrying `payload`.
+/// `uri` and `external_type` must be null-terminated UTF-8. Returns true on success.
+#[no_mangle]
+pub extern "C" fn nfc_set_emulation_ndef(
+    uri: *const c_char,
+    external_type: *const c_char,
+    payload: *const u8,
+    payload_len: usize,
+) -> bool {
+    if uri.is_null() || external_type.is_null() || (payload.is_null() && payload_len > 0) {
+        return false;
+    }
+
+    let uri_str = unsafe { CStr::from_ptr(uri) };
+    let type_str = unsafe { CStr::from_ptr(external_type) };
+    let (Ok(uri_str), Ok(type_str)) = (uri_str.to_str(), type_str.to_str()) else {
+        return false;
+    };
+    let payload_slice = if payload_len == 0 {
+        &[][..]
+    } else {
+        unsafe { std::slice::from_raw_parts(payload, payload_len) }
+    };
+
+    set_emulation_ndef(uri_str, type_str, payload_slice);
+    true
+}
+
+/// Enable or disable Host Card Emulation (tag-emulation) mode.
+#[no_mangle]
+pub extern "C" fn nfc_emulation_enable(enable: bool) {
+    set_emulation_enabled(enable);
+}
+
 /// Get firmware version string
 #[no_mangle]
 pub extern "C" fn nfc_get_firmware_version(major: *mut u8, minor: *mut u8, patch: *mut u8) {
@@ -0,0 +1,283 @@
+//! ESP32-S3 GPIO capability table and pin-role preflight checks.
+//!
+//! The CrowPanel pin-conflict saga (GPIO4/6 shorting, GPIO15 touch conflict)
+//! was only discovered after hundreds of lines of ad-hoc register poking in
+//! `main.rs`. This module gives driver init a cheap, data-driven check to
+//! run first: is this GPIO even capable of the role we're about to hand it,
+//! before any SPI/I2C peripheral is touched.
+
+use esp_idf_hal::gpio::{AnyIOPin, AnyInputPin, AnyOutputPin};
+
+/// Capabilities of a single ESP32-S3 GPIO (see the ESP32-S3 TRM, chapter 3,
+/// "IO MUX and GPIO Matrix", and the datasheet's pin list).
+#[derive(Debug, Clone, Copy)]
+pub struct GpioCaps {
+    /// Whether this index is a bonded-out GPIO on the S3 package at all.
+    pub exists: bool,
+    /// Can be configured as a digital input.
+    pub input: bool,
+    /// Can be configured as a digital output.
+    pub output: bool,
+    /// Reserved for the integrated SPI flash/PSRAM (GPIO26-37) and not
+    /// available to application code on modules that use them.
+    pub reserved: bool,
+    /// Sampled at reset to select boot mode/voltage; driving it externally
+    /// during boot can change chip behavior.
+    pub strapping: bool,
+}
+
+const fn pin(input: bool, output: bool, reserved: bool, strapping: bool) -> GpioCaps {
+    GpioCaps { exists: true, input, output, reserved, strapping }
+}
+
+const NONE: GpioCaps = GpioCaps { exists: false, input: false, output: false, reserved: false, strapping: false };
+const GENERAL: GpioCaps = pin(true, true, false, false);
+const RESERVED_FLASH: GpioCaps = pin(true, true, true, false);
+
+/// Capability table indexed by GPIO number (0-48).
+pub const GPIO_CAPS: [GpioCaps; 49] = [
+    pin(true, true, false, true),  // 0  strapping: boot mode
+    GENERAL,                       // 1
+    GENERAL,                       // 2
+    pin(true, true, false, true),  // 3  strapping: JTAG source select
+    GENERAL,                       // 4
+    GENERAL,                       // 5
+    GENERAL,                       // 6
+    GENERAL,                       // 7
+    GENERAL,                       // 8
+    GENERAL,                       // 9
+    GENERAL,                       // 10
+    GENERAL,                       // 11
+    GENERAL,                       // 12
+    GENERAL,                       // 13
+    GENERAL,                       // 14
+    GENERAL,                       // 15
+    GENERAL,                       // 16
+    GENERAL,                       // 17
+    GENERAL,                       // 18
+    GENERAL,                       // 19
+    GENERAL,                       // 20
+    GENERAL,                       // 21
+    NONE,                          // 22 not bonded out on S3
+    NONE,                          // 23 not bonded out on S3
+    NONE,                          // 24 not bonded out on S3
+    NONE,                          // 25 not bonded out on S3
+    RESERVED_FLASH,                // 26 SPI0/1 (flash)
+    RESERVED_FLASH,                // 27 SPI0/1 (flash)
+    RESERVED_FLASH,                // 28 SPI0/1 (flash)
+    RESERVED_FLASH,                // 29 SPI0/1 (flash)
+    RESERVED_FLASH,                // 30 SPI0/1 (flash)
+    RESERVED_FLASH,                // 31 SPI0/1 (flash)
+    RESERVED_FLASH,                // 32 SPI0/1 (flash)
+    RESERVED_FLASH,                // 33 octal PSRAM/flash (-R8/-N16R8 modules)
+    RESERVED_FLASH,                // 34 octal PSRAM/flash
+    RESERVED_FLASH,                // 35 octal PSRAM/flash
+    RESERVED_FLASH,                // 36 octal PSRAM/flash
+    RESERVED_FLASH,                // 37 octal PSRAM/flash
+    GENERAL,                       // 38
+    GENERAL,                       // 39
+    GENERAL,                       // 40
+    GENERAL,                       // 41
+    GENERAL,                       // 42
+    GENERAL,                       // 43 UART0 TX by default
+    GENERAL,                       // 44 UART0 RX by default
+    pin(true, true, false, true),  // 45 strapping: VDD_SPI voltage
+    pin(true, false, false, true), // 46 input-only, strapping: boot mode
+    GENERAL,                       // 47
+    GENERAL,                       // 48
+];
+
+/// Look up a GPIO's capabilities, failing for pins that don't exist on the S3 package.
+fn gpio_caps(gpio: u8) -> Result<GpioCaps, PinCapsError> {
+    match GPIO_CAPS.get(gpio as usize) {
+        Some(caps) if caps.exists => Ok(*caps),
+        _ => Err(PinCapsError::NoSuchPin(gpio)),
+    }
+}
+
+/// Whether a SPI bus is being set up as a master (us driving SCK/MOSI/CS,
+/// sampling MISO) or a slave (the roles of MISO and the rest of the bus swap).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpiRole {
+    Master,
+    Slave,
+}
+
+/// Why a requested pin assignment was rejected, naming the offending pin so
+/// init can abort cleanly with a useful message instead of silently
+/// producing garbage on the bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PinCapsError {
+    /// GPIO doesn't exist on the ESP32-S3 package.
+    NoSuchPin(u8),
+    /// GPIO is reserved for the integrated SPI flash/PSRAM.
+    Reserved(u8),
+    /// GPIO can't be driven as an output, but the requested role needs it to be.
+    NotOutputCapable(u8),
+    /// GPIO can't be sampled as an input, but the requested role needs it to be.
+    NotInputCapable(u8),
+}
+
+fn validate_output_pin(gpio: u8) -> Result<(), PinCapsError> {
+    let caps = gpio_caps(gpio)?;
+    if caps.reserved {
+        return Err(PinCapsError::Reserved(gpio));
+    }
+    if !caps.output {
+        return Err(PinCapsError::NotOutputCapable(gpio));
+    }
+    Ok(())
+}
+
+/// Validate a single GPIO used as a manually-driven output (e.g. a
+/// software-controlled chip-select, outside of the SPI peripheral's own CS).
+pub fn validate_gpio_output(gpio: u8) -> Result<(), PinCapsError> {
+    validate_output_pin(gpio)
+}
+
+fn validate_input_pin(gpio: u8) -> Result<(), PinCapsError> {
+    let caps = gpio_caps(gpio)?;
+    if caps.reserved {
+        return Err(PinCapsError::Reserved(gpio));
+    }
+    if !caps.input {
+        return Err(PinCapsError::NotInputCapable(gpio));
+    }
+    Ok(())
+}
+
+/// Validate that `sck`/`mosi`/`miso` (and `cs`, if the bus uses a hardware
+/// chip-select rather than a manually-driven GPIO) can serve an SPI bus in
+/// the given `role`. For a master, SCK/MOSI/CS must be output-capable and
+/// MISO must be input-capable; for a slave that asymmetry flips, since the
+/// bus master is the one driving SCK/MOSI/CS and only MISO is ours to drive.
+pub fn validate_spi_pins(sck: u8, mosi: u8, miso: u8, cs: Option<u8>, role: SpiRole) -> Result<(), PinCapsError> {
+    match role {
+        SpiRole::Master => {
+            validate_output_pin(sck)?;
+            validate_output_pin(mosi)?;
+            if let Some(cs) = cs {
+                validate_output_pin(cs)?;
+            }
+            validate_input_pin(miso)?;
+        }
+        SpiRole::Slave => {
+            validate_input_pin(sck)?;
+            validate_input_pin(mosi)?;
+            if let Some(cs) = cs {
+                validate_input_pin(cs)?;
+            }
+            validate_output_pin(miso)?;
+        }
+    }
+    Ok(())
+}
+
+/// Validate that `sda`/`scl` can serve an I2C bus. I2C lines are open-drain
+/// and bidirectional (driven low, released high), so both pins must support
+/// input and output.
+pub fn validate_i2c_pins(sda: u8, scl: u8) -> Result<(), PinCapsError> {
+    for gpio in [sda, scl] {
+        let caps = gpio_caps(gpio)?;
+        if caps.reserved {
+            return Err(PinCapsError::Reserved(gpio));
+        }
+        if !caps.output {
+            return Err(PinCapsError::NotOutputCapable(gpio));
+        }
+        if !caps.input {
+            return Err(PinCapsError::NotInputCapable(gpio));
+        }
+    }
+    Ok(())
+}
+
+/// Take ownership of an arbitrary GPIO by number, type-erased. For runtime
+/// pin remapping (`board_config`), where the pin numbers aren't known until
+/// NVS is read, so the usual `peripherals.pins.gpioN` owned fields (one
+/// distinct type per pin) can't be named at compile time. Callers must
+/// validate the role first (`validate_output_pin`/`validate_input_pin`/
+/// `validate_i2c_pins`) - like the rest of this module's `steal()` users,
+/// this can't check whether the GPIO is already claimed elsewhere.
+unsafe fn steal_any_io_pin(gpio: u8) -> AnyIOPin {
+    use esp_idf_hal::gpio::*;
+    match gpio {
+        0 => Gpio0::steal().into(),
+        1 => Gpio1::steal().into(),
+        2 => Gpio2::steal().into(),
+        3 => Gpio3::steal().into(),
+        4 => Gpio4::steal().into(),
+        5 => Gpio5::steal().into(),
+        6 => Gpio6::steal().into(),
+        7 => Gpio7::steal().into(),
+        8 => Gpio8::steal().into(),
+        9 => Gpio9::steal().into(),
+        10 => Gpio10::steal().into(),
+        11 => Gpio11::steal().into(),
+        12 => Gpio12::steal().into(),
+        13 => Gpio13::steal().into(),
+        14 => Gpio14::steal().into(),
+        15 => Gpio15::steal().into(),
+        16 => Gpio16::steal().into(),
+        17 => Gpio17::steal().into(),
+        18 => Gpio18::steal().into(),
+        19 => Gpio19::steal().into(),
+        20 => Gpio20::steal().into(),
+        21 => Gpio21::steal().into(),
+        26 => Gpio26::steal().into(),
+        27 => Gpio27::steal().into(),
+        28 => Gpio28::steal().into(),
+        29 => Gpio29::steal().into(),
+        30 => Gpio30::steal().into(),
+        31 => Gpio31::steal().into(),
+        32 => Gpio32::steal().into(),
+        33 => Gpio33::steal().into(),
+        34 => Gpio34::steal().into(),
+        35 => Gpio35::steal().into(),
+        36 => Gpio36::steal().into(),
+        37 => Gpio37::steal().into(),
+        38 => Gpio38::steal().into(),
+        39 => Gpio39::steal().into(),
+        40 => Gpio40::steal().into(),
+        41 => Gpio41::steal().into(),
+        42 => Gpio42::steal().into(),
+        43 => Gpio43::steal().into(),
+        44 => Gpio44::steal().into(),
+        45 => Gpio45::steal().into(),
+        46 => Gpio46::steal().into(),
+        47 => Gpio47::steal().into(),
+        48 => Gpio48::steal().into(),
+        _ => unreachable!("gpio_caps() already rejected any number outside 0-48"),
+    }
+}
+
+/// Get a type-erased output pin for an arbitrary GPIO number (manually-driven
+/// chip-select, SPI SCK/MOSI, etc), validating it first.
+pub fn any_output_pin(gpio: u8) -> Result<AnyOutputPin, PinCapsError> {
+    validate_output_pin(gpio)?;
+    Ok(unsafe { steal_any_io_pin(gpio) }.into())
+}
+
+/// Get a type-erased input pin for an arbitrary GPIO number (SPI MISO etc),
+/// validating it first.
+pub fn any_input_pin(gpio: u8) -> Result<AnyInputPin, PinCapsError> {
+    validate_input_pin(gpio)?;
+    Ok(unsafe { steal_any_io_pin(gpio) }.into())
+}
+
+/// Get a type-erased I2C-capable pin (SDA/SCL, which are bidirectional open-drain)
+/// for an arbitrary GPIO number. Callers should validate the pair with
+/// `validate_i2c_pins` first; this only re-checks the single pin.
+pub fn any_io_pin(gpio: u8) -> Result<AnyIOPin, PinCapsError> {
+    let caps = gpio_caps(gpio)?;
+    if caps.reserved {
+        return Err(PinCapsError::Reserved(gpio));
+    }
+    if !caps.output {
+        return Err(PinCapsError::NotOutputCapable(gpio));
+    }
+    if !caps.input {
+        return Err(PinCapsError::NotInputCapable(gpio));
+    }
+    Ok(unsafe { steal_any_io_pin(gpio) })
+}
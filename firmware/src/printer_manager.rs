@@ -0,0 +1,763 @@
+//! Printer Manager: live MQTT status monitor for a discovered Bambu printer
+//!
+//! `printer_discover` (in `wifi_manager.rs`) only finds a printer's static
+//! identity over UDP and stops there. This module takes that (serial, ip)
+//! pair plus a user-supplied LAN access code and turns it into a continuous
+//! monitor: a background thread holds a TLS MQTT connection to the
+//! printer's local broker (port 8883, username `bblp`, the access code as
+//! password), subscribes to `device/{serial}/report`, and requests a full
+//! `pushall` snapshot on `device/{serial}/request`.
+//!
+//! Report payloads arrive irregularly, and most of them are partial deltas
+//! carrying only the fields that changed since the last message rather than
+//! a full snapshot, so incoming fields are merged onto the last-known
+//! status (`apply_report`) instead of replacing it outright.
+
+use esp_idf_svc::mqtt::client::{EspMqttClient, EspMqttConnection, EventPayload, MqttClientConfiguration, QoS};
+use log::{error, info, warn};
+use std::ffi::{CStr, c_char, c_int};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Global printer-monitor state. Only one printer is actively monitored at
+/// a time; starting a new one replaces whatever was running.
+static PRINTER_MONITOR: Mutex<Option<PrinterMonitor>> = Mutex::new(None);
+
+/// Parsed `print` status from a Bambu printer's MQTT report, merged from the
+/// initial `pushall` snapshot and the partial deltas that follow it - a
+/// delta only updates the fields it mentions, so anything it doesn't
+/// mention keeps its last-known value.
+#[derive(Default, Clone)]
+struct PrinterStatus {
+    nozzle_temp: f32,
+    nozzle_target: f32,
+    bed_temp: f32,
+    bed_target: f32,
+    /// Bambu's `gcode_state` (e.g. "IDLE", "RUNNING", "PAUSE", "FINISH")
+    stage: String,
+    layer_num: u32,
+    total_layers: u32,
+    percent: u8,
+    remaining_time_min: u32,
+    gcode_file: String,
+    /// Flattened AMS tray inventory, keyed by (ams_id, slot_id). Only
+    /// replaced when a report's `print.ams.ams` array is actually present,
+    /// same merge-if-mentioned rule as every other field here.
+    spool_slots: Vec<Spool>,
+    /// Last bed-leveling mesh, if the printer has reported one this session.
+    /// Only replaced when a report's `print.bed_mesh` section is present.
+    bed_mesh: Option<BedMesh>,
+}
+
+impl PrinterStatus {
+    /// The printer's current AMS tray inventory.
+    fn spools(&self) -> Vec<Spool> {
+        self.spool_slots.clone()
+    }
+}
+
+/// A regular grid of measured bed Z offsets (millimeters), reshaped
+/// row-major from the flat array the printer reports.
+#[derive(Debug, Clone, Default)]
+struct BedMesh {
+    rows: usize,
+    cols: usize,
+    z: Vec<f32>,
+}
+
+impl BedMesh {
+    /// Lowest measured Z offset in the mesh.
+    fn min(&self) -> f32 {
+        self.z.iter().copied().fold(f32::INFINITY, f32::min)
+    }
+
+    /// Highest measured Z offset in the mesh.
+    fn max(&self) -> f32 {
+        self.z.iter().copied().fold(f32::NEG_INFINITY, f32::max)
+    }
+
+    /// Total deviation across the mesh, in millimeters.
+    fn z_range_mm(&self) -> f32 {
+        self.max() - self.min()
+    }
+
+    /// Rescale every value to 0.0..=1.0 for color-mapping, so a heatmap
+    /// doesn't need to redo the min/max math itself. A flat mesh (zero
+    /// range) maps everywhere to 0.0 rather than dividing by zero.
+    fn normalized(&self) -> Vec<f32> {
+        let range = self.z_range_mm();
+        if range == 0.0 {
+            return vec![0.0; self.z.len()];
+        }
+        let min = self.min();
+        self.z.iter().map(|v| (v - min) / range).collect()
+    }
+}
+
+/// One AMS tray slot: the filament loaded in it (if any) and the
+/// temperature range the slicer/printer should use for it.
+#[derive(Debug, Clone, Default)]
+struct Spool {
+    ams_id: u8,
+    slot_id: u8,
+    /// Filament type, e.g. "PLA", "PETG"
+    material: String,
+    /// RGBA tray color as parsed from the report's `tray_color` hex string
+    color_rgba: [u8; 4],
+    /// Tray UID/tag, used to recognize the same physical spool across loads
+    tray_uid: String,
+    nozzle_temp_min: u16,
+    nozzle_temp_max: u16,
+    bed_temp_min: u16,
+    bed_temp_max: u16,
+}
+
+struct PrinterMonitor {
+    serial: String,
+    /// Handle used to subscribe/publish once the worker thread's connection
+    /// reports `Connected`; the connection itself is owned by that thread.
+    client: EspMqttClient<'static>,
+    connected: bool,
+    status: PrinterStatus,
+    /// Tells the worker thread to exit after its connection is torn down.
+    running: Arc<AtomicBool>,
+}
+
+/// Open a TLS MQTT connection to `ip`'s local broker for `serial`, spawn the
+/// background worker that drives it, and replace any printer currently
+/// being monitored.
+fn start_monitor(serial: &str, ip: &str, access_code: &str) -> Result<(), String> {
+    stop_monitor_internal();
+
+    let broker_url = format!("mqtts://{}:8883", ip);
+    let mqtt_config = MqttClientConfiguration {
+        client_id: Some("spoolbuddy"),
+        username: Some("bblp"),
+        password: Some(access_code),
+        // Bambu's LAN-mode broker presents a self-signed certificate with no
+        // CA to pin against, so there's no bundle to attach here - same
+        // trust-the-local-link assumption Bambu's own LAN-mode tooling makes.
+        crt_bundle_attach: None,
+        ..Default::default()
+    };
+
+    let (client, connection) = EspMqttClient::new(&broker_url, &mqtt_config)
+        .map_err(|e| format!("Failed to create MQTT client: {:?}", e))?;
+
+    let running = Arc::new(AtomicBool::new(true));
+    let serial_owned = serial.to_string();
+    let worker_running = running.clone();
+    thread::spawn(move || run_printer_worker(connection, serial_owned, worker_running));
+
+    info!("printer_manager: monitoring {} at {}", serial, ip);
+
+    let mut guard = PRINTER_MONITOR.lock().unwrap();
+    *guard = Some(PrinterMonitor {
+        serial: serial.to_string(),
+        client,
+        connected: false,
+        status: PrinterStatus::default(),
+        running,
+    });
+
+    Ok(())
+}
+
+/// Stop the current monitor, if any. Disconnecting unblocks the worker
+/// thread's blocking `connection.next()` call so it can see `running` went
+/// false and exit on its own; it's left detached rather than joined here,
+/// since esp-mqtt doesn't guarantee a prompt wakeup from that call.
+fn stop_monitor_internal() {
+    let mut guard = PRINTER_MONITOR.lock().unwrap();
+    if let Some(mut monitor) = guard.take() {
+        monitor.running.store(false, Ordering::SeqCst);
+        let _ = monitor.client.disconnect();
+        info!("printer_manager: stopped monitoring {}", monitor.serial);
+    }
+}
+
+/// Background worker: owns the MQTT connection's event loop for the life of
+/// one monitoring session. Subscribes and requests a `pushall` snapshot once
+/// connected, and merges every report onto the shared status.
+fn run_printer_worker(mut connection: EspMqttConnection, serial: String, running: Arc<AtomicBool>) {
+    let report_topic = format!("device/{}/report", serial);
+    let request_topic = format!("device/{}/request", serial);
+
+    while running.load(Ordering::SeqCst) {
+        let event = match connection.next() {
+            Ok(event) => event,
+            Err(e) => {
+                warn!("printer_manager: MQTT event loop ended for {}: {:?}", serial, e);
+                break;
+            }
+        };
+
+        match event.payload() {
+            EventPayload::Connected(_) => {
+                info!("printer_manager: MQTT connected to {}, subscribing to {}", serial, report_topic);
+                let mut guard = PRINTER_MONITOR.lock().unwrap();
+                if let Some(monitor) = guard.as_mut() {
+                    if let Err(e) = monitor.client.subscribe(&report_topic, QoS::AtMostOnce) {
+                        error!("printer_manager: subscribe failed: {:?}", e);
+                    }
+                    let pushall = br#"{"pushing":{"sequence_id":"0","command":"pushall"}}"#;
+                    if let Err(e) = monitor.client.publish(&request_topic, QoS::AtMostOnce, false, pushall) {
+                        error!("printer_manager: pushall request failed: {:?}", e);
+                    }
+                    monitor.connected = true;
+                }
+            }
+            EventPayload::Received { topic, data, .. } => {
+                if topic == Some(report_topic.as_str()) {
+                    if let Ok(text) = std::str::from_utf8(data) {
+                        apply_report(text);
+                    } else {
+                        warn!("printer_manager: report from {} was not valid UTF-8", serial);
+                    }
+                }
+            }
+            EventPayload::Disconnected => {
+                warn!("printer_manager: MQTT disconnected from {}", serial);
+                let mut guard = PRINTER_MONITOR.lock().unwrap();
+                if let Some(monitor) = guard.as_mut() {
+                    monitor.connected = false;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    info!("printer_manager: worker thread for {} exiting", serial);
+}
+
+/// Merge a report payload's `print` object onto the monitored printer's
+/// status, overwriting only the fields this particular message mentions so
+/// a partial delta doesn't clobber fields a full `pushall` already set.
+fn apply_report(text: &str) {
+    let Some(print_obj) = extract_json_object(text, "\"print\"") else {
+        return;
+    };
+
+    let mut guard = PRINTER_MONITOR.lock().unwrap();
+    let Some(monitor) = guard.as_mut() else {
+        return;
+    };
+
+    if let Some(v) = extract_json_number_field(print_obj, "\"nozzle_temper\"") {
+        monitor.status.nozzle_temp = v;
+    }
+    if let Some(v) = extract_json_number_field(print_obj, "\"nozzle_target_temper\"") {
+        monitor.status.nozzle_target = v;
+    }
+    if let Some(v) = extract_json_number_field(print_obj, "\"bed_temper\"") {
+        monitor.status.bed_temp = v;
+    }
+    if let Some(v) = extract_json_number_field(print_obj, "\"bed_target_temper\"") {
+        monitor.status.bed_target = v;
+    }
+    if let Some(v) = extract_json_string_field(print_obj, "\"gcode_state\"") {
+        monitor.status.stage = v;
+    }
+    if let Some(v) = extract_json_number_field(print_obj, "\"layer_num\"") {
+        monitor.status.layer_num = v as u32;
+    }
+    if let Some(v) = extract_json_number_field(print_obj, "\"total_layer_num\"") {
+        monitor.status.total_layers = v as u32;
+    }
+    if let Some(v) = extract_json_number_field(print_obj, "\"mc_percent\"") {
+        monitor.status.percent = v as u8;
+    }
+    if let Some(v) = extract_json_number_field(print_obj, "\"mc_remaining_time\"") {
+        monitor.status.remaining_time_min = v as u32;
+    }
+    if let Some(v) = extract_json_string_field(print_obj, "\"gcode_file\"") {
+        monitor.status.gcode_file = v;
+    }
+    if let Some(spools) = parse_ams_inventory(print_obj) {
+        monitor.status.spool_slots = spools;
+    }
+    if let Some(mesh) = parse_bed_mesh(print_obj) {
+        monitor.status.bed_mesh = Some(mesh);
+    }
+}
+
+/// No real Bambu printer reports a grid anywhere near this large (X1/P1
+/// series top out around 7x7); this just bounds `rows * cols` well below
+/// where it could overflow `usize` or blow the ESP32's heap on a malformed
+/// or hostile MQTT report - the printer is on the user's LAN, not trusted.
+const MAX_MESH_CELLS: usize = 64 * 64;
+
+/// Parse `print.bed_mesh`'s declared grid size and flat Z-offset array into
+/// a `BedMesh`. Returns `None` if this message doesn't carry a `bed_mesh`
+/// section, so a delta that didn't touch leveling data leaves the
+/// last-known mesh untouched.
+///
+/// The flat array's length doesn't always match `row * col` exactly, so
+/// it's padded with zeros or truncated to fit rather than rejected
+/// outright - a heatmap with a few flat padding cells is more useful than
+/// no heatmap at all. Some firmware reports offsets in micrometers rather
+/// than millimeters; since a leveled bed's mesh is never more than a few
+/// millimeters of deviation, any raw magnitude over a small threshold is
+/// assumed to be micrometers and scaled down.
+fn parse_bed_mesh(print_obj: &str) -> Option<BedMesh> {
+    let mesh_obj = extract_json_object(print_obj, "\"bed_mesh\"")?;
+
+    let rows = extract_json_number_field(mesh_obj, "\"row\"")? as usize;
+    let cols = extract_json_number_field(mesh_obj, "\"col\"")? as usize;
+    let cell_count = rows.checked_mul(cols)?;
+    if cell_count > MAX_MESH_CELLS {
+        return None;
+    }
+
+    let mut z = extract_json_number_array(mesh_obj, "\"z_values\"");
+
+    const MICROMETER_THRESHOLD: f32 = 50.0;
+    if z.iter().any(|v| v.abs() > MICROMETER_THRESHOLD) {
+        for v in z.iter_mut() {
+            *v /= 1000.0;
+        }
+    }
+
+    z.resize(cell_count, 0.0);
+
+    Some(BedMesh { rows, cols, z })
+}
+
+/// Find the array value of `key` and parse it as a flat list of numbers
+/// (not objects), e.g. `print.bed_mesh.z_values`.
+fn extract_json_number_array(text: &str, key: &str) -> Vec<f32> {
+    let mut values = Vec::new();
+    let Some(key_pos) = text.find(key) else {
+        return values;
+    };
+    let after_key = &text[key_pos + key.len()..];
+    let Some(colon_pos) = after_key.find(':') else {
+        return values;
+    };
+    let after_colon = &after_key[colon_pos + 1..];
+    let Some(arr_start) = after_colon.find('[') else {
+        return values;
+    };
+    let Some(arr_end) = after_colon[arr_start + 1..].find(']') else {
+        return values;
+    };
+    let body = &after_colon[arr_start + 1..arr_start + 1 + arr_end];
+
+    for part in body.split(',') {
+        if let Ok(v) = part.trim().parse::<f32>() {
+            values.push(v);
+        }
+    }
+    values
+}
+
+/// Parse `print.ams.ams`, the array of AMS units, each holding a `tray`
+/// array of (typically four) filament slots. Returns `None` if this
+/// message's `print` object doesn't carry an `ams` section at all (a delta
+/// that didn't touch AMS state), so the caller can leave the last-known
+/// inventory alone instead of wiping it out.
+fn parse_ams_inventory(print_obj: &str) -> Option<Vec<Spool>> {
+    let ams_obj = extract_json_object(print_obj, "\"ams\"")?;
+    let units = extract_json_object_array(ams_obj, "\"ams\"");
+    if units.is_empty() {
+        return None;
+    }
+
+    let mut spools = Vec::new();
+    for unit in &units {
+        let ams_id: u8 = extract_json_string_field(unit, "\"id\"")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0);
+
+        for tray in extract_json_object_array(unit, "\"tray\"") {
+            let slot_id: u8 = extract_json_string_field(&tray, "\"id\"")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0);
+            let material = extract_json_string_field(&tray, "\"tray_type\"").unwrap_or_default();
+            let color_rgba = extract_json_string_field(&tray, "\"tray_color\"")
+                .map(|hex| parse_rgba_hex(&hex))
+                .unwrap_or([0; 4]);
+            let tray_uid = extract_json_string_field(&tray, "\"tray_uuid\"").unwrap_or_default();
+            let nozzle_temp_min = extract_json_number_field(&tray, "\"nozzle_temp_min\"").unwrap_or(0.0) as u16;
+            let nozzle_temp_max = extract_json_number_field(&tray, "\"nozzle_temp_max\"").unwrap_or(0.0) as u16;
+            let bed_temp_min = extract_json_number_field(&tray, "\"bed_temp_min\"").unwrap_or(0.0) as u16;
+            let bed_temp_max = extract_json_number_field(&tray, "\"bed_temp_max\"").unwrap_or(0.0) as u16;
+
+            spools.push(Spool {
+                ams_id,
+                slot_id,
+                material,
+                color_rgba,
+                tray_uid,
+                nozzle_temp_min,
+                nozzle_temp_max,
+                bed_temp_min,
+                bed_temp_max,
+            });
+        }
+    }
+
+    Some(spools)
+}
+
+/// Parse an 8-digit RGBA hex string (e.g. `tray_color`'s `"FF0000FF"`) into
+/// bytes, defaulting any short or non-hex byte pair to 0.
+fn parse_rgba_hex(hex: &str) -> [u8; 4] {
+    let mut out = [0u8; 4];
+    for (i, slot) in out.iter_mut().enumerate() {
+        if let Some(pair) = hex.get(i * 2..i * 2 + 2) {
+            if let Ok(v) = u8::from_str_radix(pair, 16) {
+                *slot = v;
+            }
+        }
+    }
+    out
+}
+
+/// Isolate the object value of `key` (e.g. `"print"`, or the nested `"ams"`
+/// section within it) from a report payload by brace matching, so field
+/// lookups only scan that section rather than the whole payload.
+fn extract_json_object<'a>(text: &'a str, key: &str) -> Option<&'a str> {
+    let key_pos = text.find(key)?;
+    let after_key = &text[key_pos..];
+    let colon_pos = after_key.find(':')?;
+    let after_colon = &after_key[colon_pos + 1..];
+    let obj_start = after_colon.find('{')?;
+    let body = &after_colon[obj_start..];
+
+    let mut depth = 0i32;
+    for (i, c) in body.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(&body[..=i]);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Split the array value of `key` into its top-level `{ ... }` elements by
+/// brace matching, so callers get each object's own text to scan
+/// independently (used for `print.ams.ams` and each unit's `tray` array).
+fn extract_json_object_array(text: &str, key: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let Some(key_pos) = text.find(key) else {
+        return items;
+    };
+    let after_key = &text[key_pos + key.len()..];
+    let Some(colon_pos) = after_key.find(':') else {
+        return items;
+    };
+    let after_colon = &after_key[colon_pos + 1..];
+    let Some(arr_start) = after_colon.find('[') else {
+        return items;
+    };
+    let body = &after_colon[arr_start + 1..];
+
+    let mut depth = 0i32;
+    let mut item_start: Option<usize> = None;
+    for (i, c) in body.char_indices() {
+        match c {
+            '{' => {
+                if depth == 0 {
+                    item_start = Some(i);
+                }
+                depth += 1;
+            }
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    if let Some(start) = item_start.take() {
+                        items.push(body[start..=i].to_string());
+                    }
+                }
+            }
+            ']' if depth == 0 => break,
+            _ => {}
+        }
+    }
+    items
+}
+
+/// Find `key` (including its quotes) and parse the number after its colon,
+/// stopping at the next comma, brace, or bracket.
+fn extract_json_number_field(text: &str, key: &str) -> Option<f32> {
+    let key_pos = text.find(key)?;
+    let after_key = &text[key_pos + key.len()..];
+    let colon_pos = after_key.find(':')?;
+    let rest = after_key[colon_pos + 1..].trim_start();
+    let end = rest.find(|c: char| c == ',' || c == '}' || c == ']').unwrap_or(rest.len());
+    rest[..end].trim().parse().ok()
+}
+
+/// Find `key` (including its quotes) and extract the quoted string value
+/// after its colon.
+fn extract_json_string_field(text: &str, key: &str) -> Option<String> {
+    let key_pos = text.find(key)?;
+    extract_json_string_value(&text[key_pos..])
+}
+
+/// Extract a quoted string value starting at the first colon found in
+/// `text`, handling escaped quotes within it.
+fn extract_json_string_value(text: &str) -> Option<String> {
+    let colon_pos = text.find(':')?;
+    let after_colon = &text[colon_pos + 1..];
+
+    let quote_start = after_colon.find('"')?;
+    let value_start = quote_start + 1;
+    let remaining = &after_colon[value_start..];
+
+    let mut end_pos = 0;
+    let mut chars = remaining.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            chars.next();
+            end_pos += 2;
+        } else if c == '"' {
+            break;
+        } else {
+            end_pos += c.len_utf8();
+        }
+    }
+
+    if end_pos > 0 || remaining.starts_with('"') {
+        Some(remaining[..end_pos].to_string())
+    } else {
+        None
+    }
+}
+
+// ============================================================================
+// C-callable FFI functions
+// ============================================================================
+
+/// Printer status for C interface
+#[repr(C)]
+pub struct PrinterStatusInfo {
+    pub connected: bool,
+    pub nozzle_temp: f32,
+    pub nozzle_target: f32,
+    pub bed_temp: f32,
+    pub bed_target: f32,
+    /// `gcode_state` (null-terminated), e.g. "IDLE", "RUNNING", "PAUSE"
+    pub stage: [c_char; 16],
+    pub layer_num: u32,
+    pub total_layers: u32,
+    pub percent: u8,
+    pub remaining_time_min: u32,
+    /// Current gcode filename (null-terminated)
+    pub gcode_file: [c_char; 64],
+}
+
+/// Start monitoring a printer discovered by `printer_discover`: opens a TLS
+/// MQTT connection to its local broker, subscribes to its report topic, and
+/// requests a full `pushall` snapshot. Replaces any printer already being
+/// monitored.
+/// Returns 0 on success, -1 on error.
+#[no_mangle]
+pub extern "C" fn printer_monitor_start(
+    serial: *const c_char,
+    ip: *const c_char,
+    access_code: *const c_char,
+) -> c_int {
+    if serial.is_null() {
+        error!("printer_monitor_start: serial is null");
+        return -1;
+    }
+    let serial_str = unsafe {
+        match CStr::from_ptr(serial).to_str() {
+            Ok(s) => s,
+            Err(_) => {
+                error!("printer_monitor_start: invalid serial string");
+                return -1;
+            }
+        }
+    };
+
+    if ip.is_null() {
+        error!("printer_monitor_start: ip is null");
+        return -1;
+    }
+    let ip_str = unsafe {
+        match CStr::from_ptr(ip).to_str() {
+            Ok(s) => s,
+            Err(_) => {
+                error!("printer_monitor_start: invalid ip string");
+                return -1;
+            }
+        }
+    };
+
+    if access_code.is_null() {
+        error!("printer_monitor_start: access_code is null");
+        return -1;
+    }
+    let access_code_str = unsafe {
+        match CStr::from_ptr(access_code).to_str() {
+            Ok(s) => s,
+            Err(_) => {
+                error!("printer_monitor_start: invalid access_code string");
+                return -1;
+            }
+        }
+    };
+
+    match start_monitor(serial_str, ip_str, access_code_str) {
+        Ok(()) => 0,
+        Err(e) => {
+            error!("printer_monitor_start failed: {}", e);
+            -1
+        }
+    }
+}
+
+/// Stop monitoring the current printer, if any.
+#[no_mangle]
+pub extern "C" fn printer_monitor_stop() {
+    stop_monitor_internal();
+}
+
+/// One AMS tray slot for the C interface
+#[repr(C)]
+pub struct SpoolInfo {
+    pub ams_id: u8,
+    pub slot_id: u8,
+    /// Filament type, e.g. "PLA" (null-terminated)
+    pub material: [c_char; 16],
+    pub color_rgba: [u8; 4],
+    /// Tray UID/tag (null-terminated)
+    pub tray_uid: [c_char; 32],
+    pub nozzle_temp_min: u16,
+    pub nozzle_temp_max: u16,
+    pub bed_temp_min: u16,
+    pub bed_temp_max: u16,
+}
+
+/// Copy up to `max_results` AMS tray slots from the last-known printer
+/// status into `results`.
+/// Returns the number of slots written, or -1 if no printer is being
+/// monitored.
+#[no_mangle]
+pub extern "C" fn printer_monitor_list_spools(results: *mut SpoolInfo, max_results: c_int) -> c_int {
+    if results.is_null() || max_results <= 0 {
+        return -1;
+    }
+
+    let guard = PRINTER_MONITOR.lock().unwrap();
+    let Some(monitor) = guard.as_ref() else {
+        return -1;
+    };
+
+    let spools = monitor.status.spools();
+    let count = std::cmp::min(spools.len(), max_results as usize);
+
+    for (i, spool) in spools.iter().take(count).enumerate() {
+        unsafe {
+            let out = &mut *results.add(i);
+            out.ams_id = spool.ams_id;
+            out.slot_id = spool.slot_id;
+            out.color_rgba = spool.color_rgba;
+            out.nozzle_temp_min = spool.nozzle_temp_min;
+            out.nozzle_temp_max = spool.nozzle_temp_max;
+            out.bed_temp_min = spool.bed_temp_min;
+            out.bed_temp_max = spool.bed_temp_max;
+
+            let material_bytes = spool.material.as_bytes();
+            let material_len = std::cmp::min(material_bytes.len(), 15);
+            std::ptr::copy_nonoverlapping(material_bytes.as_ptr(), out.material.as_mut_ptr() as *mut u8, material_len);
+            out.material[material_len] = 0;
+
+            let tray_uid_bytes = spool.tray_uid.as_bytes();
+            let tray_uid_len = std::cmp::min(tray_uid_bytes.len(), 31);
+            std::ptr::copy_nonoverlapping(tray_uid_bytes.as_ptr(), out.tray_uid.as_mut_ptr() as *mut u8, tray_uid_len);
+            out.tray_uid[tray_uid_len] = 0;
+        }
+    }
+
+    count as c_int
+}
+
+/// Copy the last-known bed-leveling mesh's normalized (0.0..=1.0) Z values
+/// into `normalized_out`, up to `max_values` entries, and write the grid's
+/// dimensions and absolute deviation (in millimeters) to `rows`/`cols`/
+/// `z_range_mm`.
+/// Returns the number of values written, or -1 if no printer is being
+/// monitored or it hasn't reported a bed mesh yet.
+#[no_mangle]
+pub extern "C" fn printer_monitor_get_bed_mesh(
+    rows: *mut u32,
+    cols: *mut u32,
+    z_range_mm: *mut f32,
+    normalized_out: *mut f32,
+    max_values: c_int,
+) -> c_int {
+    if rows.is_null() || cols.is_null() || z_range_mm.is_null() || normalized_out.is_null() || max_values <= 0 {
+        return -1;
+    }
+
+    let guard = PRINTER_MONITOR.lock().unwrap();
+    let Some(monitor) = guard.as_ref() else {
+        return -1;
+    };
+    let Some(mesh) = monitor.status.bed_mesh.as_ref() else {
+        return -1;
+    };
+
+    let normalized = mesh.normalized();
+    let count = std::cmp::min(normalized.len(), max_values as usize);
+
+    unsafe {
+        *rows = mesh.rows as u32;
+        *cols = mesh.cols as u32;
+        *z_range_mm = mesh.z_range_mm();
+        std::ptr::copy_nonoverlapping(normalized.as_ptr(), normalized_out, count);
+    }
+
+    count as c_int
+}
+
+/// Get the last-known printer status, merged from the `pushall` snapshot
+/// and any deltas received since.
+/// Returns 0 on success, -1 if no printer is being monitored.
+#[no_mangle]
+pub extern "C" fn printer_monitor_get_status(status: *mut PrinterStatusInfo) -> c_int {
+    if status.is_null() {
+        return -1;
+    }
+
+    let guard = PRINTER_MONITOR.lock().unwrap();
+    let Some(monitor) = guard.as_ref() else {
+        return -1;
+    };
+
+    unsafe {
+        let out = &mut *status;
+        out.connected = monitor.connected;
+        out.nozzle_temp = monitor.status.nozzle_temp;
+        out.nozzle_target = monitor.status.nozzle_target;
+        out.bed_temp = monitor.status.bed_temp;
+        out.bed_target = monitor.status.bed_target;
+        out.layer_num = monitor.status.layer_num;
+        out.total_layers = monitor.status.total_layers;
+        out.percent = monitor.status.percent;
+        out.remaining_time_min = monitor.status.remaining_time_min;
+
+        let stage_bytes = monitor.status.stage.as_bytes();
+        let stage_len = std::cmp::min(stage_bytes.len(), 15);
+        std::ptr::copy_nonoverlapping(stage_bytes.as_ptr(), out.stage.as_mut_ptr() as *mut u8, stage_len);
+        out.stage[stage_len] = 0;
+
+        let gcode_bytes = monitor.status.gcode_file.as_bytes();
+        let gcode_len = std::cmp::min(gcode_bytes.len(), 63);
+        std::ptr::copy_nonoverlapping(gcode_bytes.as_ptr(), out.gcode_file.as_mut_ptr() as *mut u8, gcode_len);
+        out.gcode_file[gcode_len] = 0;
+    }
+
+    0
+}
@@ -0,0 +1,101 @@
+//! Independent read/write SPI clock speeds for the hardware SPI3 path.
+//!
+//! `Pn5180Driver::transceive` writes a command, waits a guard delay, then
+//! reads the response back - two separate phases of one transaction that
+//! don't have to share a clock. MISO's round-trip delay, not MOSI's edge
+//! rate, is usually what limits safe read speed on a long or
+//! capacitively-loaded bus, so letting reads run slower than writes can
+//! raise write throughput without risking corrupted reads.
+//! `esp-idf-hal`'s `SpiConfig` only offers one fixed clock, so this wraps a
+//! hardware SPI device and reprograms SPI3's `CLOCK` register (the same
+//! `CLKCNT_N/H/L`/`CLKDIV_PRE` fields `spi_regs::SpiRegs::set_clock_divider`
+//! programs during bring-up) immediately before each write or read operation.
+//! This is specific to the SPI3 hardware peripheral; the bit-banged
+//! fallback (`bitbang_spi`) has no such register and runs both phases at
+//! whatever single clock it was configured with.
+
+use embedded_hal::spi::{ErrorType, Operation, SpiDevice};
+use esp_idf_hal::delay::Ets;
+
+/// Base address of the SPI3 (GPSPI3) peripheral's register block (ESP32-S3 TRM).
+const SPI3_BASE: usize = 0x6002_5000;
+/// `SPI_CLOCK_REG` offset within that block.
+const SPI_CLOCK_REG_OFFSET: usize = 0x0C;
+/// APB clock feeding the SPI clock divider on this chip.
+const APB_CLK_HZ: u32 = 80_000_000;
+
+/// Compute an `SPI_CLOCK_REG` value (`CLKDIV_PRE`=0, `CLKCNT_N`/`CLKCNT_H`/
+/// `CLKCNT_L` for a ~50% duty cycle) for a target frequency, the same
+/// scheme `spi_regs` uses for `SpiRegs::set_clock_divider`.
+/// `CLKCNT_N` is a 6-bit field, so the slowest achievable rate is
+/// `APB_CLK_HZ / 64`; lower requests are clamped to that.
+fn clock_reg_value(target_hz: u32) -> u32 {
+    let n = (APB_CLK_HZ / target_hz.max(1)).clamp(1, 64);
+    let clkcnt_n = n - 1;
+    let clkcnt_h = (n / 2).saturating_sub(1);
+    let clkcnt_l = clkcnt_n;
+    clkcnt_l | (clkcnt_h << 6) | (clkcnt_n << 12)
+}
+
+/// Write `target_hz`'s divider into SPI3's `CLOCK` register. Like the rest
+/// of this codebase's direct register pokes, this assumes SPI3's
+/// peripheral clock is already enabled and nothing else is mid-transaction
+/// on the bus.
+fn set_spi3_clock(target_hz: u32) {
+    unsafe {
+        let reg = (SPI3_BASE + SPI_CLOCK_REG_OFFSET) as *mut u32;
+        core::ptr::write_volatile(reg, clock_reg_value(target_hz));
+    }
+}
+
+/// Wraps a hardware SPI3 `SpiDevice`, reprogramming the SPI3 clock divider
+/// to `write_hz` before a write-type operation and `read_hz` before a
+/// read-type one. Implements `SpiDevice` so it drops in wherever
+/// `Pn5180Driver<SPI>` expects one, same as `BitBangSpi`.
+pub struct DualClockSpi<SPI> {
+    inner: SPI,
+    write_hz: u32,
+    read_hz: u32,
+}
+
+impl<SPI> DualClockSpi<SPI> {
+    pub fn new(inner: SPI, write_hz: u32, read_hz: u32) -> Self {
+        DualClockSpi { inner, write_hz, read_hz }
+    }
+}
+
+impl<SPI: ErrorType> ErrorType for DualClockSpi<SPI> {
+    type Error = SPI::Error;
+}
+
+impl<SPI: SpiDevice> SpiDevice for DualClockSpi<SPI> {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+        for op in operations {
+            match op {
+                Operation::Write(buf) => {
+                    set_spi3_clock(self.write_hz);
+                    self.inner.write(buf)?;
+                }
+                Operation::Read(buf) => {
+                    set_spi3_clock(self.read_hz);
+                    self.inner.read(buf)?;
+                }
+                Operation::Transfer(read, write) => {
+                    set_spi3_clock(self.read_hz);
+                    self.inner.transfer(read, write)?;
+                }
+                Operation::TransferInPlace(buf) => {
+                    set_spi3_clock(self.read_hz);
+                    self.inner.transfer_in_place(buf)?;
+                }
+                Operation::DelayNs(ns) => {
+                    let micros = ns.div_ceil(1000);
+                    if micros > 0 {
+                        Ets::delay_us(micros);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
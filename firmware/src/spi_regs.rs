@@ -0,0 +1,320 @@
+//! Typed register access for a GP-SPI peripheral's bring-up (clock/reset,
+//! clock divider, Mode 0, GPIO-matrix routing, loopback self-test).
+//!
+//! `esp-idf-hal`'s `SpiDriver` doesn't reliably enable SPI3's peripheral
+//! clock, clear its reset, or route its GPIO-matrix signals on this board.
+//! `main.rs` used to paper over that with a long sequence of inline
+//! `read_volatile`/`write_volatile` pokes hardcoded to SPI3 and the
+//! GPIO4/5/6/8 default wiring. This module gives that same sequence a name,
+//! makes each step verify itself via readback, and derives the base address,
+//! peripheral clock/reset bits, and GPIO-matrix signal numbers from a
+//! `SpiHost` selection (see `spi_routing`) instead of copy-pasted SPI3-only
+//! constants - so the same bring-up works if the PN5180 moves to SPI2.
+
+use embedded_hal::spi::SpiDevice;
+use esp_idf_hal::units::Hertz;
+
+use crate::spi_routing::SpiHost;
+
+/// `SYSTEM_PERIP_CLK_EN0_REG`/`_CLK_EN1_REG`/`_RST_EN0_REG`/`_RST_EN1_REG`:
+/// one gate/reset bit per peripheral, shared register for both hosts.
+const SYSTEM_PERIP_CLK_EN0: usize = 0x6002_600C;
+const SYSTEM_PERIP_CLK_EN1: usize = 0x6002_6010;
+const SYSTEM_PERIP_RST_EN0: usize = 0x6002_6020;
+const SYSTEM_PERIP_RST_EN1: usize = 0x6002_6024;
+
+/// GP-SPI register block offsets (ESP32-S3 TRM, chapter 26, "GP-SPI") -
+/// identical layout on SPI2 and SPI3, just a different base address.
+const SPI_CLOCK_REG_OFFSET: usize = 0x0C;
+const SPI_USER_REG_OFFSET: usize = 0x10;
+const SPI_MISC_REG_OFFSET: usize = 0x3C;
+/// `SPI_CLOCK_REG` bit 31: bypass the divider and run straight off the APB clock.
+const CLK_EQU_SYSCLK_BIT: u32 = 31;
+/// `SPI_MISC_REG` bit 17: loop the GPSPI engine's MOSI output back to its own
+/// MISO input internally, so a transfer can be exercised with no device on
+/// the bus.
+const SPI_LOOPBACK_BIT: u32 = 17;
+
+/// APB clock feeding the SPI clock divider on this chip.
+const APB_CLK_HZ: u32 = 80_000_000;
+
+const GPIO_ENABLE_W1TC_REG: usize = 0x6000_4028;
+/// `GPIO_FUNCn_OUT_SEL_CFG_REG`, one per GPIO, `n = gpio`.
+const GPIO_FUNC_OUT_SEL_BASE: usize = 0x6000_4554;
+/// `GPIO_FUNCn_IN_SEL_CFG_REG`, one per peripheral input signal, `n = signal`.
+const GPIO_FUNC_IN_SEL_BASE: usize = 0x6000_4154;
+const FUNC_IN_SEL_ENABLE_BIT: u32 = 7;
+
+/// IO_MUX base address; each GPIO's config register sits at `0x04 + 4*gpio`
+/// (ESP32-S3 TRM, chapter 6, "IO MUX and GPIO Matrix" - confirmed against
+/// this board's wiring: GPIO4->0x14, GPIO5->0x18, GPIO6->0x1C).
+const IO_MUX_BASE: usize = 0x6000_9000;
+const IO_MUX_FUN_WPU_BIT: u32 = 8;
+const IO_MUX_FUN_WPD_BIT: u32 = 7;
+/// Drive strength field used by the SCK/MOSI fixup below: 0=5mA, 1=10mA,
+/// 2=20mA, 3=40mA.
+const IO_MUX_DRV_SHIFT: u32 = 8;
+const IO_MUX_DRV_MASK: u32 = 0x3;
+
+/// Per-host constants that used to be copy-pasted SPI3 literals: register
+/// block base address, peripheral clock/reset gate bits, and GPIO-matrix
+/// signal numbers for SCK/MOSI/MISO (ESP32-S3 TRM, chapter 10, "GPIO
+/// Matrix" - SPI2 and SPI3 each have their own signal numbers).
+struct PeripheralMap {
+    base: usize,
+    clk_en0_bit: u32,
+    clk_en1_bit: u32,
+    rst_en0_bit: u32,
+    rst_en1_bit: u32,
+    sck_out_signal: u32,
+    mosi_out_signal: u32,
+    miso_in_signal: u32,
+}
+
+/// `clk_en1_bit`/`rst_en1_bit` mirror `clk_en0_bit`/`rst_en0_bit` on this
+/// board (see `SpiRegs::enable_clock`'s doc comment) at an offset that holds
+/// for both hosts. SPI3's GPIO-matrix signal numbers (114/115/116) were
+/// already in use for the default wiring; SPI2's (62/63/65) are what an
+/// earlier diagnostic-only read elsewhere in the tree used for its MISO
+/// signal (63) before this table existed to get it right.
+fn peripheral_map(host: SpiHost) -> PeripheralMap {
+    match host {
+        SpiHost::Spi2 => PeripheralMap {
+            base: 0x6002_4000,
+            clk_en0_bit: 6,
+            clk_en1_bit: 22,
+            rst_en0_bit: 6,
+            rst_en1_bit: 22,
+            sck_out_signal: 62,
+            mosi_out_signal: 65,
+            miso_in_signal: 63,
+        },
+        SpiHost::Spi3 => PeripheralMap {
+            base: 0x6002_5000,
+            clk_en0_bit: 7,
+            clk_en1_bit: 23,
+            rst_en0_bit: 7,
+            rst_en1_bit: 23,
+            sck_out_signal: 114,
+            mosi_out_signal: 115,
+            miso_in_signal: 116,
+        },
+    }
+}
+
+fn io_mux_reg(gpio: u8) -> usize {
+    IO_MUX_BASE + 0x04 + 4 * gpio as usize
+}
+
+fn func_out_sel_reg(gpio: u8) -> usize {
+    GPIO_FUNC_OUT_SEL_BASE + 4 * gpio as usize
+}
+
+fn func_in_sel_reg(signal: u32) -> usize {
+    GPIO_FUNC_IN_SEL_BASE + 4 * signal as usize
+}
+
+unsafe fn read(addr: usize) -> u32 {
+    core::ptr::read_volatile(addr as *const u32)
+}
+
+unsafe fn write(addr: usize, value: u32) {
+    core::ptr::write_volatile(addr as *mut u32, value)
+}
+
+unsafe fn modify(addr: usize, f: impl FnOnce(u32) -> u32) {
+    let value = f(read(addr));
+    write(addr, value);
+}
+
+/// Clock divider (`CLKCNT_N`, 6 bits) for a target frequency; the slowest
+/// achievable rate is `APB_CLK_HZ / 64`, so lower requests clamp to that.
+fn clock_divider_n(target_hz: u32) -> u32 {
+    (APB_CLK_HZ / target_hz.max(1)).clamp(1, 64)
+}
+
+/// `SPI_CLOCK_REG` value for divider `n` (`CLKDIV_PRE` = 0, `CLKCNT_H` set
+/// for a ~50% duty cycle).
+fn clock_reg_value(n: u32) -> u32 {
+    let clkcnt_n = n - 1;
+    let clkcnt_h = (n / 2).saturating_sub(1);
+    let clkcnt_l = clkcnt_n;
+    clkcnt_l | (clkcnt_h << 6) | (clkcnt_n << 12)
+}
+
+/// Result of `SpiRegs::loopback_self_test`.
+#[derive(Debug, Clone, Default)]
+pub struct LoopbackTestResult {
+    /// True if the pattern echoed back with no mismatches.
+    pub ok: bool,
+    /// `(index, sent, received)` for every pattern byte that didn't echo
+    /// back unchanged. Empty both when the test passed and when the
+    /// loopback bit or transfer itself failed outright (see `ok`).
+    pub mismatches: Vec<(usize, u8, u8)>,
+}
+
+/// Handle onto one GP-SPI host's peripheral-clock/reset and GPIO-matrix
+/// registers. Holds only the resolved `PeripheralMap` for `host`; every
+/// method acts on the addresses/bits/signals that map describes.
+pub struct SpiRegs {
+    map: PeripheralMap,
+}
+
+impl SpiRegs {
+    pub fn new(host: SpiHost) -> Self {
+        SpiRegs { map: peripheral_map(host) }
+    }
+
+    /// Enable this host's peripheral clock in both `PERIP_CLK_EN0` (the
+    /// documented gate) and `PERIP_CLK_EN1` (which this board also needs
+    /// set). Returns whether both gate bits read back set afterward.
+    pub fn enable_clock(&self) -> bool {
+        unsafe {
+            modify(SYSTEM_PERIP_CLK_EN0, |v| v | (1 << self.map.clk_en0_bit));
+            modify(SYSTEM_PERIP_CLK_EN1, |v| v | (1 << self.map.clk_en1_bit));
+            (read(SYSTEM_PERIP_CLK_EN0) >> self.map.clk_en0_bit) & 1 == 1
+                && (read(SYSTEM_PERIP_CLK_EN1) >> self.map.clk_en1_bit) & 1 == 1
+        }
+    }
+
+    /// Clear this host's peripheral reset in `PERIP_RST_EN0`/`PERIP_RST_EN1`.
+    /// Returns whether both reset bits read back clear afterward.
+    pub fn clear_reset(&self) -> bool {
+        unsafe {
+            modify(SYSTEM_PERIP_RST_EN0, |v| v & !(1 << self.map.rst_en0_bit));
+            modify(SYSTEM_PERIP_RST_EN1, |v| v & !(1 << self.map.rst_en1_bit));
+            (read(SYSTEM_PERIP_RST_EN0) >> self.map.rst_en0_bit) & 1 == 0
+                && (read(SYSTEM_PERIP_RST_EN1) >> self.map.rst_en1_bit) & 1 == 0
+        }
+    }
+
+    /// True if `SPI_CLOCK_REG` is still bypassing the divider and running
+    /// straight off the 80 MHz APB clock - either nothing has programmed a
+    /// divider yet, or the peripheral reset to that state.
+    pub fn clock_is_full_speed(&self) -> bool {
+        unsafe { (read(self.map.base + SPI_CLOCK_REG_OFFSET) >> CLK_EQU_SYSCLK_BIT) & 1 == 1 }
+    }
+
+    /// Program `SPI_CLOCK_REG`'s divider for `target_hz`. Returns the clock
+    /// actually programmed (see `clock_divider_n`'s rounding), or `None` if
+    /// the register didn't read back as written.
+    pub fn set_clock_divider(&self, target_hz: u32) -> Option<Hertz> {
+        let n = clock_divider_n(target_hz);
+        let clk_val = clock_reg_value(n);
+        let reg = self.map.base + SPI_CLOCK_REG_OFFSET;
+        unsafe {
+            write(reg, clk_val);
+            if read(reg) != clk_val {
+                return None;
+            }
+        }
+        Some(Hertz(APB_CLK_HZ / n))
+    }
+
+    /// Program `SPI_USER_REG` for a basic Mode 0 read+write transaction
+    /// (`USR_MOSI`, `USR_MISO`, `CS_SETUP`, `CS_HOLD`). Returns whether the
+    /// register read back as written.
+    pub fn configure_mode0(&self) -> bool {
+        let user_val: u32 = (1 << 27) | (1 << 28) | (1 << 7) | (1 << 8);
+        let reg = self.map.base + SPI_USER_REG_OFFSET;
+        unsafe {
+            write(reg, user_val);
+            read(reg) == user_val
+        }
+    }
+
+    /// Read `SPI_MISC_REG`'s current loopback bit state.
+    pub fn loopback_enabled(&self) -> bool {
+        unsafe { (read(self.map.base + SPI_MISC_REG_OFFSET) >> SPI_LOOPBACK_BIT) & 1 == 1 }
+    }
+
+    /// Set (or clear) the GPSPI engine's internal MOSI->MISO loopback bit.
+    /// Returns whether the bit read back as requested.
+    pub fn set_loopback(&self, enabled: bool) -> bool {
+        let reg = self.map.base + SPI_MISC_REG_OFFSET;
+        unsafe {
+            modify(reg, |v| if enabled { v | (1 << SPI_LOOPBACK_BIT) } else { v & !(1 << SPI_LOOPBACK_BIT) });
+        }
+        self.loopback_enabled() == enabled
+    }
+
+    /// Enable the GPSPI engine's internal loopback, transfer a fixed test
+    /// pattern (0x00, 0xFF, 0xAA, 0x55, then walking-ones bytes 0x01 through
+    /// 0x80), and check
+    /// every byte echoes back unchanged - this isolates "is the SPI engine
+    /// clocking and shifting correctly" from "is the PN5180 responding",
+    /// since no device needs to be on the bus for it. Restores the loopback
+    /// bit to whatever it was set to beforehand, whether or not the test
+    /// passed.
+    pub fn loopback_self_test<SPI: SpiDevice>(&self, spi: &mut SPI) -> LoopbackTestResult {
+        const PATTERN: [u8; 12] = [0x00, 0xFF, 0xAA, 0x55, 0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80];
+
+        let was_enabled = self.loopback_enabled();
+        if !self.set_loopback(true) {
+            return LoopbackTestResult { ok: false, mismatches: Vec::new() };
+        }
+
+        let mut buf = PATTERN;
+        let transfer_ok = spi.transfer_in_place(&mut buf).is_ok();
+        self.set_loopback(was_enabled);
+
+        if !transfer_ok {
+            return LoopbackTestResult { ok: false, mismatches: Vec::new() };
+        }
+
+        let mismatches: Vec<(usize, u8, u8)> = PATTERN
+            .iter()
+            .zip(buf.iter())
+            .enumerate()
+            .filter(|(_, (sent, got))| sent != got)
+            .map(|(i, (&sent, &got))| (i, sent, got))
+            .collect();
+
+        LoopbackTestResult { ok: mismatches.is_empty(), mismatches }
+    }
+
+    /// Route this host's SCK/MOSI outputs and MISO input through the GPIO
+    /// matrix onto `sck`/`mosi`/`miso`, disabling `miso`'s output driver
+    /// first so it can act as an input. Also ensures `miso` has its pull-up
+    /// enabled, since a remapped MISO needs the same floating-line
+    /// protection the default wiring does. Returns whether every touched
+    /// register read back as written.
+    pub fn route_signals(&self, sck: u8, mosi: u8, miso: u8) -> bool {
+        unsafe {
+            write(GPIO_ENABLE_W1TC_REG, 1 << miso);
+            let miso_sel = (1 << FUNC_IN_SEL_ENABLE_BIT) | miso as u32;
+            write(func_in_sel_reg(self.map.miso_in_signal), miso_sel);
+            write(func_out_sel_reg(sck), self.map.sck_out_signal);
+            write(func_out_sel_reg(mosi), self.map.mosi_out_signal);
+
+            (read(func_in_sel_reg(self.map.miso_in_signal)) & 0x1FF) == miso_sel
+                && (read(func_out_sel_reg(sck)) & 0x1FF) == self.map.sck_out_signal
+                && (read(func_out_sel_reg(mosi)) & 0x1FF) == self.map.mosi_out_signal
+                && self.enable_miso_pullup(miso)
+        }
+    }
+
+    /// Enable `pin`'s internal pull-up (and disable its pull-down) via
+    /// IO_MUX - used for MISO, to distinguish the PN5180 actively driving
+    /// the line low from it floating. Returns whether the pull-up bit read
+    /// back set.
+    pub fn enable_miso_pullup(&self, pin: u8) -> bool {
+        let reg = io_mux_reg(pin);
+        unsafe {
+            modify(reg, |v| (v | (1 << IO_MUX_FUN_WPU_BIT)) & !(1 << IO_MUX_FUN_WPD_BIT));
+            (read(reg) >> IO_MUX_FUN_WPU_BIT) & 1 == 1
+        }
+    }
+
+    /// Set `pin`'s IO_MUX drive strength (`level` 0-3, 5mA-40mA) - used to
+    /// give SCK/MOSI more margin on long jumper-wire runs. Returns whether
+    /// the field read back as written.
+    pub fn set_drive_strength(&self, pin: u8, level: u32) -> bool {
+        let reg = io_mux_reg(pin);
+        let level = level & IO_MUX_DRV_MASK;
+        unsafe {
+            modify(reg, |v| (v & !(IO_MUX_DRV_MASK << IO_MUX_DRV_SHIFT)) | (level << IO_MUX_DRV_SHIFT));
+            (read(reg) >> IO_MUX_DRV_SHIFT) & IO_MUX_DRV_MASK == level
+        }
+    }
+}
@@ -0,0 +1,116 @@
+//! IO-MUX native routing vs GPIO-matrix detection for the ESP32-S3 GP-SPI
+//! peripherals, and the safe clock ceiling that follows from it.
+//!
+//! `spi_regs::SpiRegs::route_signals` routes SCK/MOSI/MISO onto whichever
+//! pins `board_config` names. Whether a
+//! signal takes its IO_MUX native pin or goes through the GPIO matrix isn't
+//! just a curiosity: matrix routing adds a clock of latency to the signal
+//! path, which caps the maximum clock the bus can reliably run at well
+//! below the IO-MUX ceiling (ESP32-S3 TRM, chapter 26, "GP-SPI"). A
+//! CrowPanel remap onto arbitrary GPIOs will almost always land on
+//! matrix-routed pins, since the native pins for SPI2/SPI3 are few and
+//! mostly already spoken for (SPI3's happen to sit in the octal PSRAM/flash
+//! range - see `pin_caps::GPIO_CAPS`).
+
+use esp_idf_hal::units::Hertz;
+
+/// Which GP-SPI peripheral the bus is using; each has its own fixed set of
+/// IO_MUX native pins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpiHost {
+    Spi2,
+    Spi3,
+}
+
+/// IO_MUX native pin for each SPI signal on a given host.
+struct NativePins {
+    sck: u8,
+    mosi: u8,
+    miso: u8,
+}
+
+/// ESP32-S3 TRM Table 3-5 ("IO MUX Pin Functions"): each GP-SPI host's
+/// direct-connect IO_MUX pins for CLK/D(MOSI)/Q(MISO).
+fn native_pins(host: SpiHost) -> NativePins {
+    match host {
+        SpiHost::Spi2 => NativePins { sck: 12, mosi: 11, miso: 13 },
+        SpiHost::Spi3 => NativePins { sck: 36, mosi: 35, miso: 37 },
+    }
+}
+
+/// Maximum clock recommended for a bus where every signal takes its IO_MUX
+/// native path - still conservative relative to the peripheral's absolute
+/// ceiling, since this board uses long jumper-wire runs rather than a tight
+/// PCB trace.
+const NATIVE_MAX_HZ: u32 = 20_000_000;
+
+/// Maximum clock recommended once any signal is routed through the GPIO
+/// matrix - the extra clock of round-trip latency the matrix adds is what
+/// actually limits this, not the jumper wires.
+const MATRIX_MAX_HZ: u32 = 8_000_000;
+
+/// Per-signal routing outcome and the resulting recommended clock ceiling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpiRoutingReport {
+    pub sck_native: bool,
+    pub mosi_native: bool,
+    pub miso_native: bool,
+    pub recommended_max_hz: Hertz,
+}
+
+impl SpiRoutingReport {
+    /// True if any signal had to fall back to the GPIO matrix - the reason
+    /// `recommended_max_hz` is below `NATIVE_MAX_HZ`.
+    pub fn matrix_routed(&self) -> bool {
+        !(self.sck_native && self.mosi_native && self.miso_native)
+    }
+}
+
+/// Work out whether `sck`/`mosi`/`miso` take `host`'s IO_MUX native path or
+/// fall back to the GPIO matrix, and recommend a maximum clock accordingly.
+pub fn analyze(host: SpiHost, sck: u8, mosi: u8, miso: u8) -> SpiRoutingReport {
+    let native = native_pins(host);
+    let sck_native = sck == native.sck;
+    let mosi_native = mosi == native.mosi;
+    let miso_native = miso == native.miso;
+
+    let recommended_max_hz = if sck_native && mosi_native && miso_native {
+        NATIVE_MAX_HZ
+    } else {
+        MATRIX_MAX_HZ
+    };
+
+    SpiRoutingReport { sck_native, mosi_native, miso_native, recommended_max_hz: Hertz(recommended_max_hz) }
+}
+
+/// Clamp `requested_hz` to what `analyze` recommends for this pin
+/// assignment, logging a warning (naming the matrix-routed signals) when the
+/// requested clock had to be capped.
+pub fn clamp_clock(host: SpiHost, sck: u8, mosi: u8, miso: u8, requested_hz: u32) -> Hertz {
+    let report = analyze(host, sck, mosi, miso);
+
+    if requested_hz <= report.recommended_max_hz.0 {
+        return Hertz(requested_hz);
+    }
+
+    if report.matrix_routed() {
+        log::warn!(
+            "NFC SPI clock {} Hz exceeds the {} Hz matrix-routed ceiling (SCK native={} MOSI native={} MISO native={}), capping to {} Hz",
+            requested_hz,
+            MATRIX_MAX_HZ,
+            report.sck_native,
+            report.mosi_native,
+            report.miso_native,
+            report.recommended_max_hz.0
+        );
+    } else {
+        log::warn!(
+            "NFC SPI clock {} Hz exceeds the {} Hz IO-MUX-native ceiling, capping to {} Hz",
+            requested_hz,
+            NATIVE_MAX_HZ,
+            report.recommended_max_hz.0
+        );
+    }
+
+    report.recommended_max_hz
+}
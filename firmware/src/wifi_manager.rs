@@ -4,18 +4,49 @@
 //! The connection runs in a background thread to avoid blocking the UI.
 //! Credentials are persisted to NVS for auto-reconnect on boot.
 
+use embedded_svc::io::{Read, Write};
 use esp_idf_hal::modem::Modem;
-use esp_idf_svc::eventloop::EspSystemEventLoop;
+use esp_idf_svc::eventloop::{EspSubscription, EspSystemEventLoop, System};
+use esp_idf_svc::http::Method;
+use esp_idf_svc::http::server::{Configuration as HttpServerConfig, EspHttpServer};
 use esp_idf_svc::nvs::{EspDefaultNvsPartition, EspNvs};
-use esp_idf_svc::wifi::{AuthMethod, BlockingWifi, ClientConfiguration, Configuration, EspWifi};
+use esp_idf_svc::wifi::{
+    AccessPointConfiguration, AuthMethod, BlockingWifi, ClientConfiguration, Configuration,
+    EspWifi, WifiDeviceId, WifiEvent,
+};
 use log::{info, warn, error};
 use std::ffi::{CStr, c_char, c_int};
-use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// SoftAP password for the fallback provisioning portal. WPA2 requires at
+/// least 8 characters; this is intentionally fixed since the portal's own
+/// page is how a phone gets its *real* network credentials.
+const AP_PASSWORD: &str = "spoolbuddy";
+
+/// Exponential-backoff schedule for the auto-reconnect worker: starting
+/// delay and cap between retries after an unexpected disconnect.
+const RECONNECT_BASE_BACKOFF_SECS: u64 = 1;
+const RECONNECT_MAX_BACKOFF_SECS: u64 = 60;
 
 // NVS keys for WiFi credentials
 const NVS_NAMESPACE: &str = "wifi";
 const NVS_KEY_SSID: &str = "ssid";
 const NVS_KEY_PASSWORD: &str = "password";
+// Saved-network list: `net_count` plus indexed `ssid{n}`/`pwd{n}` keys
+const NVS_KEY_NET_COUNT: &str = "net_count";
+const MAX_SAVED_NETWORKS: usize = 8;
+const NVS_KEY_POWER_SAVE: &str = "power_save";
+
+/// Modem power-save mode, mirrors esp-idf's `wifi_ps_type_t`: full power
+/// (lowest latency, highest idle draw), min-modem (sleeps between DTIM
+/// beacons; the default here), and max-modem (sleeps more aggressively, at
+/// the cost of higher latency waking the radio).
+const POWER_SAVE_NONE: u8 = 0;
+const POWER_SAVE_MIN_MODEM: u8 = 1;
+const POWER_SAVE_MAX_MODEM: u8 = 2;
 
 /// WiFi connection state
 #[derive(Debug, Clone, PartialEq)]
@@ -23,10 +54,41 @@ pub enum WifiState {
     Uninitialized,
     Disconnected,
     Connecting,
-    Connected { ip: [u8; 4], rssi: i8 },
+    Connected { ip: [u8; 4], rssi: i8, auth_mode: u8, bssid: [u8; 6], channel: u8 },
+    /// Radio is running as a SoftAP serving the provisioning captive portal.
+    AccessPoint { ssid: String },
     Error(String),
 }
 
+/// Handle to the background work backing the AP-fallback captive portal
+/// (the DNS responder thread and the HTTP server it keeps alive), so
+/// `stop_ap_portal` can shut both down and hand the radio back.
+struct ApPortalHandle {
+    stop: Arc<AtomicBool>,
+    dns_thread: Option<thread::JoinHandle<()>>,
+    http_server: Option<EspHttpServer<'static>>,
+}
+
+/// Commands sent to the background WiFi worker thread, which owns the
+/// blocking connect flow and the exponential-backoff retry loop so that
+/// `wifi_connect` and the `WifiEvent` subscription below can both return
+/// immediately instead of blocking the caller.
+enum WifiWorkerCommand {
+    /// Connect with new credentials (from `start_connect`).
+    Connect { ssid: String, password: String },
+    /// Record the credentials already connected (e.g. by the boot-time
+    /// saved-network selector) without triggering a connect attempt, so a
+    /// later disconnect still knows what to retry.
+    SetCurrent { ssid: String, password: String },
+    /// `WifiEvent::StaDisconnected` fired; reconnect with the last known
+    /// credentials if auto-reconnect is on.
+    StaDisconnected,
+    /// `wifi_disconnect` was called; stop retrying and forget the current
+    /// credentials so the resulting disconnect event doesn't bounce back.
+    ManualDisconnect,
+    SetAutoReconnect(bool),
+}
+
 /// Global WiFi manager state
 struct WifiManager {
     state: WifiState,
@@ -36,6 +98,24 @@ struct WifiManager {
     wifi: Option<BlockingWifi<EspWifi<'static>>>,
     // NVS partition for storing credentials
     nvs: Option<EspDefaultNvsPartition>,
+    // Set while the AP-fallback provisioning portal is running
+    ap_portal: Option<ApPortalHandle>,
+    // Saved networks as (ssid, password) pairs, mirrored to NVS under the
+    // indexed `ssid{n}`/`pwd{n}` keys
+    networks: Vec<(String, String)>,
+    // Channel to the background connect/auto-reconnect worker
+    worker_tx: Option<mpsc::Sender<WifiWorkerCommand>>,
+    // Shared with the worker so `wifi_set_auto_reconnect` takes effect
+    // immediately, including mid-backoff
+    auto_reconnect: Arc<AtomicBool>,
+    // Keeps the `WifiEvent` subscription alive for as long as the manager is
+    _wifi_event_sub: Option<EspSubscription<'static, System>>,
+    // Set by `configure_enterprise_eap`; consumed by the next `do_connect`
+    // so it uses `AuthMethod::WPA2Enterprise` instead of guessing from a scan
+    enterprise_pending: bool,
+    // Modem power-save mode (POWER_SAVE_NONE/MIN_MODEM/MAX_MODEM), persisted
+    // to NVS and re-applied to the driver on every connect
+    power_save: u8,
 }
 
 // Global WiFi manager - protected by mutex
@@ -59,32 +139,85 @@ pub fn init_wifi_system(
     let wifi = BlockingWifi::wrap(esp_wifi, sysloop.clone())
         .map_err(|e| format!("Failed to wrap WiFi: {:?}", e))?;
 
-    // Load saved credentials from NVS
-    let (saved_ssid, saved_password) = load_credentials_from_nvs(nvs.as_ref());
+    // Load the saved-network list, migrating the legacy single ssid/password
+    // pair into it the first time this runs after an upgrade.
+    let (legacy_ssid, legacy_password) = load_legacy_credentials_from_nvs(nvs.as_ref());
+    let mut networks = load_saved_networks(nvs.as_ref());
+    if networks.is_empty() && !legacy_ssid.is_empty() {
+        info!("Migrating legacy saved WiFi credentials into the saved-network list");
+        networks.push((legacy_ssid, legacy_password));
+        save_saved_networks(nvs.as_ref(), &networks);
+    }
+
+    let power_save = load_power_save_mode(nvs.as_ref());
+
+    // Background worker owns the blocking connect flow and the
+    // exponential-backoff auto-reconnect loop; `WifiEvent::StaDisconnected`
+    // feeds it so a dropped AP gets retried without anyone polling for it.
+    let auto_reconnect = Arc::new(AtomicBool::new(true));
+    let (worker_tx, worker_rx) = mpsc::channel();
+    thread::spawn({
+        let auto_reconnect = auto_reconnect.clone();
+        move || run_wifi_worker(worker_rx, auto_reconnect)
+    });
+
+    let event_tx = worker_tx.clone();
+    let wifi_event_sub = sysloop
+        .subscribe(move |event: &WifiEvent| {
+            match event {
+                WifiEvent::StaDisconnected => {
+                    let _ = event_tx.send(WifiWorkerCommand::StaDisconnected);
+                }
+                WifiEvent::StaConnected => {
+                    info!("WifiEvent: STA connected, awaiting IP");
+                }
+                _ => {}
+            }
+        })
+        .map_err(|e| format!("Failed to subscribe to WiFi events: {:?}", e))?;
 
     let mut manager = WIFI_MANAGER.lock().unwrap();
     *manager = Some(WifiManager {
         state: WifiState::Disconnected,
-        ssid: saved_ssid.clone(),
-        password: saved_password.clone(),
+        ssid: String::new(),
+        password: String::new(),
         wifi: Some(wifi),
         nvs,
+        ap_portal: None,
+        networks: networks.clone(),
+        worker_tx: Some(worker_tx),
+        auto_reconnect,
+        _wifi_event_sub: Some(wifi_event_sub),
+        enterprise_pending: false,
+        power_save,
     });
 
     info!("WiFi subsystem initialized");
 
-    // Auto-connect if we have saved credentials
-    if !saved_ssid.is_empty() {
-        info!("Found saved WiFi credentials, auto-connecting to: {}", saved_ssid);
-        drop(manager); // Release lock before calling start_connect
-        let _ = start_connect(&saved_ssid, &saved_password);
+    // Connect to the strongest saved network currently in range if we have
+    // any; otherwise there's nothing to try, so fall back to the AP
+    // provisioning portal so a blank device can still be configured.
+    if !networks.is_empty() {
+        info!("Found {} saved network(s), selecting the strongest visible one", networks.len());
+        drop(manager); // Release lock before calling connect_best_saved_network
+        if let Err(e) = connect_best_saved_network(&networks) {
+            warn!("{}", e);
+        }
+    } else {
+        drop(manager); // Release lock before calling start_ap_portal
+        info!("No saved WiFi credentials, starting AP-fallback provisioning portal");
+        if let Err(e) = start_ap_portal() {
+            error!("Failed to start AP-fallback portal: {}", e);
+        }
     }
 
     Ok(())
 }
 
-/// Load WiFi credentials from NVS
-fn load_credentials_from_nvs(nvs: Option<&EspDefaultNvsPartition>) -> (String, String) {
+/// Load the pre-multi-network single ssid/password pair from NVS, used only
+/// to migrate a device that was provisioned before saved-network lists
+/// existed.
+fn load_legacy_credentials_from_nvs(nvs: Option<&EspDefaultNvsPartition>) -> (String, String) {
     let Some(nvs_partition) = nvs else {
         return (String::new(), String::new());
     };
@@ -108,101 +241,481 @@ fn load_credentials_from_nvs(nvs: Option<&EspDefaultNvsPartition>) -> (String, S
     };
 
     if !ssid.is_empty() {
-        info!("Loaded saved WiFi SSID: {}", ssid);
+        info!("Loaded legacy saved WiFi SSID: {}", ssid);
     }
 
     (ssid, password)
 }
 
-/// Save WiFi credentials to NVS
-fn save_credentials_to_nvs(ssid: &str, password: &str) {
-    let manager_guard = WIFI_MANAGER.lock().unwrap();
-    let Some(manager) = manager_guard.as_ref() else {
+/// Load all saved networks from the indexed `ssid{n}`/`pwd{n}` NVS keys
+/// under a `net_count` entry recording how many slots are in use.
+fn load_saved_networks(nvs: Option<&EspDefaultNvsPartition>) -> Vec<(String, String)> {
+    let Some(nvs_partition) = nvs else {
+        return Vec::new();
+    };
+
+    let Ok(nvs) = EspNvs::new(nvs_partition.clone(), NVS_NAMESPACE, true) else {
+        warn!("Failed to open NVS namespace for reading saved networks");
+        return Vec::new();
+    };
+
+    let mut count_buf = [0u8; 8];
+    let count: usize = match nvs.get_str(NVS_KEY_NET_COUNT, &mut count_buf) {
+        Ok(Some(s)) => s.parse().unwrap_or(0),
+        _ => 0,
+    };
+
+    let mut networks = Vec::new();
+    for i in 0..count.min(MAX_SAVED_NETWORKS) {
+        let mut ssid_buf = [0u8; 64];
+        let mut password_buf = [0u8; 64];
+
+        let ssid = match nvs.get_str(&nvs_ssid_key(i), &mut ssid_buf) {
+            Ok(Some(s)) if !s.is_empty() => s.to_string(),
+            _ => continue,
+        };
+        let password = match nvs.get_str(&nvs_pwd_key(i), &mut password_buf) {
+            Ok(Some(s)) => s.to_string(),
+            _ => String::new(),
+        };
+        networks.push((ssid, password));
+    }
+
+    if !networks.is_empty() {
+        info!("Loaded {} saved network(s) from NVS", networks.len());
+    }
+    networks
+}
+
+/// Persist the full saved-network list to NVS, overwriting every indexed
+/// slot up to `net_count`.
+fn save_saved_networks(nvs: Option<&EspDefaultNvsPartition>, networks: &[(String, String)]) {
+    let Some(nvs_partition) = nvs else {
+        warn!("No NVS partition available for saving networks");
+        return;
+    };
+
+    let Ok(nvs) = EspNvs::new(nvs_partition.clone(), NVS_NAMESPACE, true) else {
+        error!("Failed to open NVS namespace for writing saved networks");
         return;
     };
-    let Some(nvs_partition) = manager.nvs.as_ref() else {
-        warn!("No NVS partition available for saving credentials");
+
+    let count = networks.len().min(MAX_SAVED_NETWORKS);
+    if let Err(e) = nvs.set_str(NVS_KEY_NET_COUNT, &count.to_string()) {
+        error!("Failed to save network count to NVS: {:?}", e);
         return;
+    }
+
+    for (i, (ssid, password)) in networks.iter().take(count).enumerate() {
+        if let Err(e) = nvs.set_str(&nvs_ssid_key(i), ssid) {
+            error!("Failed to save network {} SSID to NVS: {:?}", i, e);
+        }
+        if let Err(e) = nvs.set_str(&nvs_pwd_key(i), password) {
+            error!("Failed to save network {} password to NVS: {:?}", i, e);
+        }
+    }
+
+    info!("Saved {} network(s) to NVS", count);
+}
+
+fn nvs_ssid_key(index: usize) -> String {
+    format!("ssid{}", index)
+}
+
+fn nvs_pwd_key(index: usize) -> String {
+    format!("pwd{}", index)
+}
+
+/// Load the persisted power-save mode from NVS, defaulting to min-modem
+/// sleep (the best balance of idle current vs. latency for a battery-backed
+/// device) if nothing has been saved yet.
+fn load_power_save_mode(nvs: Option<&EspDefaultNvsPartition>) -> u8 {
+    let Some(nvs_partition) = nvs else {
+        return POWER_SAVE_MIN_MODEM;
+    };
+
+    let Ok(nvs) = EspNvs::new(nvs_partition.clone(), NVS_NAMESPACE, true) else {
+        warn!("Failed to open NVS namespace for reading power-save mode");
+        return POWER_SAVE_MIN_MODEM;
     };
 
-    let nvs_clone = nvs_partition.clone();
-    drop(manager_guard); // Release lock before NVS operations
+    let mut mode_buf = [0u8; 4];
+    match nvs.get_str(NVS_KEY_POWER_SAVE, &mut mode_buf) {
+        Ok(Some(s)) => s.parse().unwrap_or(POWER_SAVE_MIN_MODEM),
+        _ => POWER_SAVE_MIN_MODEM,
+    }
+}
 
-    let Ok(nvs) = EspNvs::new(nvs_clone, NVS_NAMESPACE, true) else {
-        error!("Failed to open NVS namespace for writing");
+/// Persist the power-save mode to NVS so it survives a reboot.
+fn save_power_save_mode(nvs: Option<&EspDefaultNvsPartition>, mode: u8) {
+    let Some(nvs_partition) = nvs else {
+        warn!("No NVS partition available for saving power-save mode");
         return;
     };
 
-    if let Err(e) = nvs.set_str(NVS_KEY_SSID, ssid) {
-        error!("Failed to save SSID to NVS: {:?}", e);
+    let Ok(nvs) = EspNvs::new(nvs_partition.clone(), NVS_NAMESPACE, true) else {
+        error!("Failed to open NVS namespace for writing power-save mode");
         return;
+    };
+
+    if let Err(e) = nvs.set_str(NVS_KEY_POWER_SAVE, &mode.to_string()) {
+        error!("Failed to save power-save mode to NVS: {:?}", e);
     }
+}
 
-    if let Err(e) = nvs.set_str(NVS_KEY_PASSWORD, password) {
-        error!("Failed to save password to NVS: {:?}", e);
-        return;
+/// Push a power-save mode to the driver via `esp_wifi_set_ps`, the same
+/// direct `esp_idf_sys` path `configure_enterprise_eap` uses for driver
+/// features `esp-idf-svc`'s safe wrapper doesn't expose. Must be called
+/// after the WiFi driver has been started.
+fn apply_power_save(_wifi: &mut BlockingWifi<EspWifi<'static>>, mode: u8) -> Result<(), String> {
+    let ps_type = match mode {
+        POWER_SAVE_NONE => esp_idf_sys::wifi_ps_type_t_WIFI_PS_NONE,
+        POWER_SAVE_MAX_MODEM => esp_idf_sys::wifi_ps_type_t_WIFI_PS_MAX_MODEM,
+        _ => esp_idf_sys::wifi_ps_type_t_WIFI_PS_MIN_MODEM,
+    };
+
+    let err = unsafe { esp_idf_sys::esp_wifi_set_ps(ps_type) };
+    if err != 0 {
+        return Err(format!("esp_wifi_set_ps failed: esp_err {}", err));
+    }
+    Ok(())
+}
+
+/// Insert or update a saved network and persist the full list to NVS. Once
+/// `MAX_SAVED_NETWORKS` is reached, a new SSID is rejected rather than
+/// silently evicting an existing one.
+fn add_network_internal(ssid: &str, password: &str) -> Result<(), String> {
+    let mut manager_guard = WIFI_MANAGER.lock().unwrap();
+    let manager = manager_guard.as_mut().ok_or("WiFi not initialized")?;
+
+    if let Some(entry) = manager.networks.iter_mut().find(|(s, _)| s == ssid) {
+        entry.1 = password.to_string();
+    } else {
+        if manager.networks.len() >= MAX_SAVED_NETWORKS {
+            return Err(format!("Saved network list is full (max {})", MAX_SAVED_NETWORKS));
+        }
+        manager.networks.push((ssid.to_string(), password.to_string()));
+    }
+
+    let networks = manager.networks.clone();
+    let nvs = manager.nvs.clone();
+    drop(manager_guard);
+    save_saved_networks(nvs.as_ref(), &networks);
+    Ok(())
+}
+
+/// Remove a saved network by SSID and persist the updated list to NVS.
+fn remove_network_internal(ssid: &str) -> Result<(), String> {
+    let mut manager_guard = WIFI_MANAGER.lock().unwrap();
+    let manager = manager_guard.as_mut().ok_or("WiFi not initialized")?;
+
+    let before = manager.networks.len();
+    manager.networks.retain(|(s, _)| s != ssid);
+    if manager.networks.len() == before {
+        return Err("Network not found".to_string());
+    }
+
+    let networks = manager.networks.clone();
+    let nvs = manager.nvs.clone();
+    drop(manager_guard);
+    save_saved_networks(nvs.as_ref(), &networks);
+    Ok(())
+}
+
+/// Try each saved network that's currently visible, strongest
+/// `signal_strength` first (Fuchsia's `network_selection` and ESPurna's
+/// multi-network fallback do the same), falling back to the next candidate
+/// on a connection failure. Saved networks the scan didn't see (all of
+/// them, if the scan failed) are tried afterward in saved order as a last
+/// resort.
+///
+/// This is the one place that still calls `do_connect` directly instead of
+/// going through the worker: it's only used once at boot, and needs each
+/// candidate's pass/fail result synchronously to decide whether to try the
+/// next one. Once a candidate connects, the worker is told via
+/// `SetCurrent` so a later disconnect still auto-reconnects correctly; if
+/// every candidate fails, the strongest one is queued with the worker so
+/// its backoff loop keeps retrying in the background.
+fn connect_best_saved_network(networks: &[(String, String)]) -> Result<(), String> {
+    let ranked = rank_saved_networks_by_signal(networks);
+
+    for (ssid, password) in &ranked {
+        info!("Attempting saved network: {}", ssid);
+        match do_connect(ssid, password) {
+            Ok((ip, rssi, auth_mode, bssid, channel)) => {
+                let mut manager_guard = WIFI_MANAGER.lock().unwrap();
+                if let Some(manager) = manager_guard.as_mut() {
+                    manager.state = WifiState::Connected { ip, rssi, auth_mode, bssid, channel };
+                    manager.ssid = ssid.clone();
+                    manager.password = password.clone();
+                    if let Some(tx) = &manager.worker_tx {
+                        let _ = tx.send(WifiWorkerCommand::SetCurrent {
+                            ssid: ssid.clone(),
+                            password: password.clone(),
+                        });
+                    }
+                }
+                drop(manager_guard);
+                info!("WiFi connected! IP: {}.{}.{}.{} RSSI: {}dBm", ip[0], ip[1], ip[2], ip[3], rssi);
+                let _ = add_network_internal(ssid, password);
+                return Ok(());
+            }
+            Err(e) => {
+                warn!("Saved network {} failed to connect ({}), trying next candidate", ssid, e);
+            }
+        }
     }
 
-    info!("WiFi credentials saved to NVS");
+    if let Some((ssid, password)) = ranked.into_iter().next() {
+        warn!("No saved network connected at boot; queueing {} for background retry", ssid);
+        let _ = start_connect(&ssid, &password);
+    }
+
+    Err("No saved network could be connected".to_string())
 }
 
-/// Start WiFi connection (non-blocking, runs in background)
+/// Scan for visible APs and order the saved networks by descending
+/// `signal_strength` among those seen, with any saved networks that didn't
+/// show up in the scan appended afterward in their saved order.
+fn rank_saved_networks_by_signal(networks: &[(String, String)]) -> Vec<(String, String)> {
+    if networks.is_empty() {
+        return Vec::new();
+    }
+
+    let mut manager_guard = WIFI_MANAGER.lock().unwrap();
+    let Some(manager) = manager_guard.as_mut() else {
+        return networks.to_vec();
+    };
+    let Some(wifi) = manager.wifi.as_mut() else {
+        return networks.to_vec();
+    };
+
+    if !wifi.is_started().unwrap_or(false) {
+        let scan_config = Configuration::Client(ClientConfiguration {
+            ssid: "".try_into().unwrap_or_default(),
+            ..Default::default()
+        });
+        if let Err(e) = wifi.set_configuration(&scan_config) {
+            warn!("Could not set config for network-selection scan: {:?}", e);
+        }
+        if let Err(e) = wifi.start() {
+            warn!("Failed to start WiFi for network-selection scan: {:?}", e);
+            return networks.to_vec();
+        }
+    }
+
+    let scan_results = match wifi.scan() {
+        Ok(results) => results,
+        Err(e) => {
+            warn!("Network-selection scan failed, trying saved networks in saved order: {:?}", e);
+            return networks.to_vec();
+        }
+    };
+
+    let mut seen = vec![false; networks.len()];
+    let mut ranked: Vec<(i8, usize)> = networks
+        .iter()
+        .enumerate()
+        .filter_map(|(i, (ssid, _))| {
+            scan_results
+                .iter()
+                .filter(|ap| &ap.ssid == ssid)
+                .map(|ap| ap.signal_strength)
+                .max()
+                .map(|rssi| (rssi, i))
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.0.cmp(&a.0));
+
+    let mut result: Vec<(String, String)> = ranked
+        .into_iter()
+        .map(|(_, i)| {
+            seen[i] = true;
+            networks[i].clone()
+        })
+        .collect();
+
+    for (i, network) in networks.iter().enumerate() {
+        if !seen[i] {
+            result.push(network.clone());
+        }
+    }
+
+    result
+}
+
+/// Request a WiFi connection. Non-blocking: hands the credentials to the
+/// background worker thread and returns as soon as they're queued. The
+/// worker performs the actual blocking connect and, if the link later
+/// drops, owns the exponential-backoff auto-reconnect loop for it.
 fn start_connect(ssid: &str, password: &str) -> Result<(), String> {
     let ssid_owned = ssid.to_string();
     let password_owned = password.to_string();
 
-    // Update state to Connecting
-    {
+    let worker_tx = {
         let mut manager_guard = WIFI_MANAGER.lock().unwrap();
         let manager = manager_guard.as_mut().ok_or("WiFi not initialized")?;
         manager.state = WifiState::Connecting;
         manager.ssid = ssid_owned.clone();
         manager.password = password_owned.clone();
-    }
-
-    info!("Starting WiFi connection to: {}", ssid_owned);
+        manager.worker_tx.clone().ok_or("WiFi worker not running")?
+    };
 
-    // Do the connection in the current context (we'll make it truly async later if needed)
-    // For now, we'll do a blocking connect but update state properly
-    let result = do_connect(&ssid_owned, &password_owned);
+    info!("Queueing WiFi connection to: {}", ssid_owned);
+    worker_tx
+        .send(WifiWorkerCommand::Connect { ssid: ssid_owned, password: password_owned })
+        .map_err(|_| "WiFi worker channel closed".to_string())
+}
 
-    // Update state based on result
-    {
-        let mut manager_guard = WIFI_MANAGER.lock().unwrap();
-        if let Some(manager) = manager_guard.as_mut() {
-            match result {
-                Ok((ip, rssi)) => {
-                    manager.state = WifiState::Connected { ip, rssi };
-                    info!("WiFi connected! IP: {}.{}.{}.{} RSSI: {}dBm", ip[0], ip[1], ip[2], ip[3], rssi);
+/// Background WiFi worker: the sole owner of the connect/backoff flow.
+/// Reads `WifiWorkerCommand`s off `rx` for as long as the channel lives
+/// (i.e. for the life of the program - the manager never tears it down).
+fn run_wifi_worker(rx: mpsc::Receiver<WifiWorkerCommand>, auto_reconnect: Arc<AtomicBool>) {
+    let mut current: Option<(String, String)> = None;
+    let generation = Arc::new(AtomicU64::new(0));
+
+    while let Ok(command) = rx.recv() {
+        match command {
+            WifiWorkerCommand::SetAutoReconnect(enabled) => {
+                auto_reconnect.store(enabled, Ordering::Relaxed);
+                info!("WiFi auto-reconnect {}", if enabled { "enabled" } else { "disabled" });
+            }
+            WifiWorkerCommand::SetCurrent { ssid, password } => {
+                current = Some((ssid, password));
+            }
+            WifiWorkerCommand::ManualDisconnect => {
+                current = None;
+                generation.fetch_add(1, Ordering::SeqCst);
+            }
+            WifiWorkerCommand::Connect { ssid, password } => {
+                current = Some((ssid.clone(), password.clone()));
+                let my_generation = generation.fetch_add(1, Ordering::SeqCst) + 1;
+                spawn_connect_attempt(ssid, password, my_generation, generation.clone(), auto_reconnect.clone());
+            }
+            WifiWorkerCommand::StaDisconnected => {
+                if !auto_reconnect.load(Ordering::Relaxed) {
+                    continue;
+                }
+                if matches!(get_state(), WifiState::AccessPoint { .. }) {
+                    continue; // Provisioning portal owns the radio right now
                 }
-                Err(ref e) => {
-                    manager.state = WifiState::Error(e.clone());
-                    warn!("WiFi connection failed: {}", e);
+                let Some((ssid, password)) = current.clone() else {
+                    continue; // No known network to retry (e.g. AP portal path)
+                };
+
+                {
+                    let mut manager_guard = WIFI_MANAGER.lock().unwrap();
+                    if let Some(manager) = manager_guard.as_mut() {
+                        manager.state = WifiState::Connecting;
+                    }
                 }
+
+                let my_generation = generation.fetch_add(1, Ordering::SeqCst) + 1;
+                spawn_connect_attempt(ssid, password, my_generation, generation.clone(), auto_reconnect.clone());
             }
         }
     }
+}
 
-    // Save credentials to NVS after successful connection
-    if result.is_ok() {
-        save_credentials_to_nvs(&ssid_owned, &password_owned);
-    }
+/// Spawn a detached retry loop for one connect attempt: try `do_connect`,
+/// and on failure keep retrying with exponential backoff (1s, 2s, 4s...
+/// capped at `RECONNECT_MAX_BACKOFF_SECS`, with jitter) until it succeeds,
+/// auto-reconnect is turned off, or `generation` has moved on (a newer
+/// `Connect`/disconnect superseded this one).
+fn spawn_connect_attempt(
+    ssid: String,
+    password: String,
+    my_generation: u64,
+    generation: Arc<AtomicU64>,
+    auto_reconnect: Arc<AtomicBool>,
+) {
+    thread::spawn(move || {
+        let mut backoff_secs = RECONNECT_BASE_BACKOFF_SECS;
+
+        loop {
+            if generation.load(Ordering::SeqCst) != my_generation {
+                return; // Superseded
+            }
 
-    result.map(|_| ())
+            info!("WiFi worker connecting to: {}", ssid);
+            match do_connect(&ssid, &password) {
+                Ok((ip, rssi, auth_mode, bssid, channel)) => {
+                    if generation.load(Ordering::SeqCst) != my_generation {
+                        return; // Superseded while the connect was in flight
+                    }
+                    let mut manager_guard = WIFI_MANAGER.lock().unwrap();
+                    if let Some(manager) = manager_guard.as_mut() {
+                        manager.state = WifiState::Connected { ip, rssi, auth_mode, bssid, channel };
+                    }
+                    drop(manager_guard);
+                    info!("WiFi connected! IP: {}.{}.{}.{} RSSI: {}dBm", ip[0], ip[1], ip[2], ip[3], rssi);
+                    let _ = add_network_internal(&ssid, &password);
+                    return;
+                }
+                Err(e) => {
+                    if generation.load(Ordering::SeqCst) == my_generation {
+                        let mut manager_guard = WIFI_MANAGER.lock().unwrap();
+                        if let Some(manager) = manager_guard.as_mut() {
+                            manager.state = WifiState::Error(e.clone());
+                        }
+                    }
+                    warn!("WiFi connection attempt failed: {}", e);
+                }
+            }
+
+            if !auto_reconnect.load(Ordering::Relaxed) || generation.load(Ordering::SeqCst) != my_generation {
+                return;
+            }
+
+            let delay = Duration::from_secs(backoff_secs) + Duration::from_millis(backoff_jitter_ms(backoff_secs));
+            info!("Retrying {} in ~{}s", ssid, backoff_secs);
+            thread::sleep(delay);
+            if !auto_reconnect.load(Ordering::Relaxed) {
+                return; // Turned off while waiting out the backoff
+            }
+            backoff_secs = (backoff_secs * 2).min(RECONNECT_MAX_BACKOFF_SECS);
+        }
+    });
 }
 
-/// Actually perform the WiFi connection (blocking)
-fn do_connect(ssid: &str, password: &str) -> Result<([u8; 4], i8), String> {
+/// A little jitter scaled to the current backoff step, so that several
+/// devices that lost the same AP don't all retry in lockstep. Not
+/// cryptographic - just spreads retries out using the hardware timer as a
+/// cheap pseudo-random source (no `rand` dependency in this tree).
+fn backoff_jitter_ms(backoff_secs: u64) -> u64 {
+    let ticks = unsafe { esp_idf_sys::esp_timer_get_time() } as u64;
+    ticks % (backoff_secs * 250 + 1)
+}
+
+/// Actually perform the WiFi connection (blocking). Returns the IP, RSSI,
+/// the auth mode code (see `auth_mode_code`) that was actually used, and
+/// the BSSID/channel of the AP actually associated with.
+fn do_connect(ssid: &str, password: &str) -> Result<([u8; 4], i8, u8, [u8; 6], u8), String> {
     let mut manager_guard = WIFI_MANAGER.lock().unwrap();
     let manager = manager_guard.as_mut().ok_or("WiFi not initialized")?;
 
+    // `wifi_connect_enterprise` sets this one-shot flag via
+    // `configure_enterprise_eap` so this connect uses the EAP credentials
+    // already pushed to the driver instead of guessing from a scan.
+    let enterprise = manager.enterprise_pending;
+    manager.enterprise_pending = false;
+    let power_save = manager.power_save;
+
     let wifi = manager.wifi.as_mut().ok_or("WiFi handle not available")?;
 
+    let auth_method = if enterprise {
+        AuthMethod::WPA2Enterprise
+    } else if password.is_empty() {
+        AuthMethod::None
+    } else {
+        detect_auth_method(wifi, ssid)
+    };
+
     // Configure WiFi
     let config = Configuration::Client(ClientConfiguration {
         ssid: ssid.try_into().map_err(|_| "SSID too long")?,
         bssid: None,
-        auth_method: if password.is_empty() { AuthMethod::None } else { AuthMethod::WPA2Personal },
+        auth_method,
         password: password.try_into().map_err(|_| "Password too long")?,
         channel: None,
         ..Default::default()
@@ -215,6 +728,12 @@ fn do_connect(ssid: &str, password: &str) -> Result<([u8; 4], i8), String> {
     wifi.start()
         .map_err(|e| format!("Failed to start WiFi: {:?}", e))?;
 
+    // `esp_wifi_set_ps` only takes effect once the driver is started; a
+    // failure here is logged but not fatal to the connection attempt.
+    if let Err(e) = apply_power_save(wifi, power_save) {
+        warn!("Failed to apply power-save mode: {}", e);
+    }
+
     // Connect
     wifi.connect()
         .map_err(|e| format!("Failed to connect: {:?}", e))?;
@@ -230,10 +749,114 @@ fn do_connect(ssid: &str, password: &str) -> Result<([u8; 4], i8), String> {
     let ip = ip_info.ip;
     let ip_bytes = [ip.octets()[0], ip.octets()[1], ip.octets()[2], ip.octets()[3]];
 
-    // Get RSSI (signal strength)
-    let rssi = get_current_rssi_internal(wifi);
+    // Get live link info (RSSI/BSSID/channel) for the AP we actually
+    // associated with, rather than trusting whichever entry happened to be
+    // first in the last scan.
+    let (rssi, bssid, channel) = match query_sta_ap_info() {
+        Some((rssi, bssid, channel)) => (rssi, bssid, channel),
+        None => {
+            warn!("esp_wifi_sta_get_ap_info failed, falling back to an approximate RSSI");
+            (get_current_rssi_internal(wifi), [0u8; 6], 0)
+        }
+    };
 
-    Ok((ip_bytes, rssi))
+    Ok((ip_bytes, rssi, auth_mode_code(auth_method), bssid, channel))
+}
+
+/// Scan for `ssid` and return its advertised auth method, so `do_connect`
+/// doesn't have to assume WPA2Personal (which silently fails against
+/// WPA3-only or enterprise APs). Falls back to `WPA2WPA3Personal` (mixed
+/// mode, the most forgiving non-enterprise choice) when the network isn't
+/// currently visible in the scan, or didn't advertise an auth method.
+fn detect_auth_method(wifi: &mut BlockingWifi<EspWifi<'static>>, ssid: &str) -> AuthMethod {
+    if !wifi.is_started().unwrap_or(false) {
+        let scan_config = Configuration::Client(ClientConfiguration {
+            ssid: "".try_into().unwrap_or_default(),
+            ..Default::default()
+        });
+        if let Err(e) = wifi.set_configuration(&scan_config) {
+            warn!("Could not set config for auth-method scan: {:?}", e);
+        }
+        if let Err(e) = wifi.start() {
+            warn!("Failed to start WiFi for auth-method scan: {:?}", e);
+            return AuthMethod::WPA2WPA3Personal;
+        }
+    }
+
+    match wifi.scan() {
+        Ok(results) => results
+            .into_iter()
+            .find(|ap| ap.ssid == ssid)
+            .and_then(|ap| ap.auth_method)
+            .unwrap_or(AuthMethod::WPA2WPA3Personal),
+        Err(e) => {
+            warn!("Auth-method scan failed for {}, assuming mixed WPA2/WPA3: {:?}", ssid, e);
+            AuthMethod::WPA2WPA3Personal
+        }
+    }
+}
+
+/// Map an `AuthMethod` to the numeric code reported in `WifiStatus`:
+/// 0=Open, 1=WEP, 2=WPA, 3=WPA2, 4=WPA3, 5=mixed WPA2/WPA3,
+/// 6=Enterprise, 7=WAPI.
+fn auth_mode_code(auth: AuthMethod) -> u8 {
+    match auth {
+        AuthMethod::None => 0,
+        AuthMethod::WEP => 1,
+        AuthMethod::WPA => 2,
+        AuthMethod::WPA2Personal => 3,
+        AuthMethod::WPA3Personal => 4,
+        AuthMethod::WPA2WPA3Personal => 5,
+        AuthMethod::WPA2Enterprise => 6,
+        AuthMethod::WAPIPersonal => 7,
+        _ => 3,
+    }
+}
+
+/// Push WPA2-Enterprise (802.1X) credentials to the underlying esp-idf EAP
+/// client and enable enterprise mode on the STA interface. `esp-idf-svc`'s
+/// `ClientConfiguration` has no enterprise fields, so this goes straight
+/// through `esp_idf_sys`, the same way `board_config.rs` calls
+/// `esp_idf_sys::esp_restart()` directly for things the safe wrapper
+/// doesn't expose. Sets `enterprise_pending` so the next `do_connect` call
+/// uses `AuthMethod::WPA2Enterprise` instead of guessing from a scan.
+fn configure_enterprise_eap(identity: &str, username: &str, password: &str) -> Result<(), String> {
+    let identity_c = std::ffi::CString::new(identity).map_err(|_| "Identity contains a NUL byte")?;
+    let username_c = std::ffi::CString::new(username).map_err(|_| "Username contains a NUL byte")?;
+    let password_c = std::ffi::CString::new(password).map_err(|_| "Password contains a NUL byte")?;
+
+    unsafe {
+        let err = esp_idf_sys::esp_eap_client_set_identity(
+            identity_c.as_ptr() as *const u8,
+            identity_c.as_bytes().len() as i32,
+        );
+        if err != 0 {
+            return Err(format!("Failed to set EAP identity: esp_err {}", err));
+        }
+        let err = esp_idf_sys::esp_eap_client_set_username(
+            username_c.as_ptr() as *const u8,
+            username_c.as_bytes().len() as i32,
+        );
+        if err != 0 {
+            return Err(format!("Failed to set EAP username: esp_err {}", err));
+        }
+        let err = esp_idf_sys::esp_eap_client_set_password(
+            password_c.as_ptr() as *const u8,
+            password_c.as_bytes().len() as i32,
+        );
+        if err != 0 {
+            return Err(format!("Failed to set EAP password: esp_err {}", err));
+        }
+        let err = esp_idf_sys::esp_wifi_sta_enterprise_enable();
+        if err != 0 {
+            return Err(format!("Failed to enable WPA2-Enterprise: esp_err {}", err));
+        }
+    }
+
+    let mut manager_guard = WIFI_MANAGER.lock().unwrap();
+    let manager = manager_guard.as_mut().ok_or("WiFi not initialized")?;
+    manager.enterprise_pending = true;
+    Ok(())
 }
 
 /// Get current RSSI from WiFi driver (internal helper)
@@ -246,44 +869,494 @@ fn get_current_rssi_internal(wifi: &mut BlockingWifi<EspWifi<'static>>) -> i8 {
                 return ap.signal_strength;
             }
         }
-        Err(_) => {}
+        Err(_) => {}
+    }
+    -50 // Default moderate signal if we can't get it
+}
+
+/// Query the live RSSI/BSSID/channel of the AP we're actually associated
+/// with via `esp_wifi_sta_get_ap_info`, rather than the first entry of
+/// whatever the last scan happened to return (which may not even be the AP
+/// we're connected to). Returns `None` if not currently associated.
+fn query_sta_ap_info() -> Option<(i8, [u8; 6], u8)> {
+    let mut ap_info: esp_idf_sys::wifi_ap_record_t = unsafe { std::mem::zeroed() };
+    let err = unsafe { esp_idf_sys::esp_wifi_sta_get_ap_info(&mut ap_info) };
+    if err != 0 {
+        return None;
+    }
+    Some((ap_info.rssi, ap_info.bssid, ap_info.primary))
+}
+
+/// Get current WiFi state
+fn get_state() -> WifiState {
+    let manager_guard = WIFI_MANAGER.lock().unwrap();
+    match manager_guard.as_ref() {
+        Some(manager) => manager.state.clone(),
+        None => WifiState::Uninitialized,
+    }
+}
+
+/// Get the currently configured power-save mode
+fn get_power_save_mode() -> u8 {
+    let manager_guard = WIFI_MANAGER.lock().unwrap();
+    match manager_guard.as_ref() {
+        Some(manager) => manager.power_save,
+        None => POWER_SAVE_MIN_MODEM,
+    }
+}
+
+// ============================================================================
+// AP-fallback provisioning portal (ESPurna-style `ApMode::Fallback`)
+// ============================================================================
+
+/// Build the SoftAP SSID from the device's AP-mode MAC address, e.g.
+/// `SpoolBuddy-3A2F`, so multiple units on the same bench don't collide.
+fn ap_ssid(wifi: &mut BlockingWifi<EspWifi<'static>>) -> String {
+    match wifi.wifi().driver().get_mac(WifiDeviceId::Ap) {
+        Ok(mac) => format!("SpoolBuddy-{:02X}{:02X}", mac[4], mac[5]),
+        Err(e) => {
+            warn!("Could not read AP MAC address, using generic SSID: {:?}", e);
+            "SpoolBuddy-0000".to_string()
+        }
+    }
+}
+
+/// Reconfigure the radio into SoftAP mode and bring up the captive portal
+/// (HTTP provisioning page + DNS responder). Called at boot when NVS has no
+/// saved credentials; repeated-station-failure fallback will hook in here
+/// once auto-reconnect retry counting exists. A no-op if already running.
+fn start_ap_portal() -> Result<(), String> {
+    let mut manager_guard = WIFI_MANAGER.lock().unwrap();
+    let manager = manager_guard.as_mut().ok_or("WiFi not initialized")?;
+
+    if manager.ap_portal.is_some() {
+        return Ok(());
+    }
+
+    let wifi = manager.wifi.as_mut().ok_or("WiFi handle not available")?;
+    let ssid = ap_ssid(wifi);
+    info!("Starting AP-fallback portal: {}", ssid);
+
+    let config = Configuration::AccessPoint(AccessPointConfiguration {
+        ssid: ssid.as_str().try_into().map_err(|_| "AP SSID too long")?,
+        password: AP_PASSWORD.try_into().map_err(|_| "AP password too long")?,
+        auth_method: AuthMethod::WPA2Personal,
+        channel: 1,
+        ..Default::default()
+    });
+
+    wifi.set_configuration(&config).map_err(|e| format!("Failed to set AP config: {:?}", e))?;
+    wifi.start().map_err(|e| format!("Failed to start AP: {:?}", e))?;
+
+    let http_server = start_provisioning_http_server()?;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let dns_stop = stop.clone();
+    let dns_thread = thread::spawn(move || run_captive_dns(dns_stop));
+
+    manager.state = WifiState::AccessPoint { ssid: ssid.clone() };
+    manager.ap_portal = Some(ApPortalHandle {
+        stop,
+        dns_thread: Some(dns_thread),
+        http_server: Some(http_server),
+    });
+
+    info!("AP-fallback portal up ({}); open http://192.168.4.1/ to configure", ssid);
+    Ok(())
+}
+
+/// Tear the captive portal down: stop the DNS thread and drop the HTTP
+/// server. Leaves the radio's mode to whatever the caller does next -
+/// normally `start_connect` switches it back to station mode.
+fn stop_ap_portal() -> Result<(), String> {
+    let portal = {
+        let mut manager_guard = WIFI_MANAGER.lock().unwrap();
+        let manager = manager_guard.as_mut().ok_or("WiFi not initialized")?;
+        manager.ap_portal.take()
+    };
+
+    let Some(mut portal) = portal else {
+        return Ok(()); // Not running
+    };
+
+    portal.stop.store(true, Ordering::Relaxed);
+    if let Some(handle) = portal.dns_thread.take() {
+        let _ = handle.join();
+    }
+    drop(portal.http_server.take());
+
+    info!("AP-fallback portal stopped");
+    Ok(())
+}
+
+/// Provisioning page served on `/`: a single form posting the chosen
+/// SSID/password to `/connect`.
+const PROVISIONING_PAGE: &str = r#"<!DOCTYPE html>
+<html><head><title>SpoolBuddy Setup</title></head>
+<body>
+<h1>Connect SpoolBuddy to WiFi</h1>
+<form method="POST" action="/connect">
+  <label>Network name (SSID)<br><input name="ssid" maxlength="32" required></label><br>
+  <label>Password<br><input name="password" type="password" maxlength="64"></label><br>
+  <button type="submit">Connect</button>
+</form>
+</body></html>"#;
+
+/// Start the captive-portal HTTP server: the page above on `/`, and a
+/// `/connect` handler that hands the submitted credentials to the normal
+/// `start_connect` (which records the network via `add_network_internal`)
+/// path and tears the portal
+/// down once that succeeds.
+fn start_provisioning_http_server() -> Result<EspHttpServer<'static>, String> {
+    let config = HttpServerConfig::default();
+    let mut server = EspHttpServer::new(&config)
+        .map_err(|e| format!("Failed to start HTTP server: {:?}", e))?;
+
+    server
+        .fn_handler("/", Method::Get, |request| {
+            request.into_ok_response()?.write_all(PROVISIONING_PAGE.as_bytes())
+        })
+        .map_err(|e| format!("Failed to register / handler: {:?}", e))?;
+
+    server
+        .fn_handler("/connect", Method::Post, |mut request| {
+            let mut body = [0u8; 256];
+            let mut len = 0;
+            while len < body.len() {
+                match request.read(&mut body[len..]) {
+                    Ok(0) => break,
+                    Ok(n) => len += n,
+                    Err(_) => break,
+                }
+            }
+
+            let (ssid, password) = parse_form_credentials(
+                std::str::from_utf8(&body[..len]).unwrap_or(""),
+            );
+
+            if ssid.is_empty() {
+                request.into_status_response(400)?.write_all(b"Missing SSID")?;
+                return Ok(());
+            }
+
+            match start_connect(&ssid, &password) {
+                Ok(()) => {
+                    request
+                        .into_ok_response()?
+                        .write_all(b"Connecting... the portal will close once it succeeds.")?;
+                    // The worker connects in the background, so wait for it
+                    // to actually report Connected (with a timeout) before
+                    // tearing the AP down - otherwise a bad password would
+                    // silently strand the device with no way back in.
+                    thread::spawn(|| {
+                        let deadline = Duration::from_secs(15);
+                        let poll_interval = Duration::from_millis(500);
+                        let mut waited = Duration::ZERO;
+                        while waited < deadline {
+                            if matches!(get_state(), WifiState::Connected { .. }) {
+                                let _ = stop_ap_portal();
+                                return;
+                            }
+                            thread::sleep(poll_interval);
+                            waited += poll_interval;
+                        }
+                        warn!("Provisioning connect did not succeed within {:?}, leaving portal up", deadline);
+                    });
+                }
+                Err(e) => {
+                    warn!("Provisioning connect failed: {}", e);
+                    request
+                        .into_status_response(502)?
+                        .write_all(format!("Connect failed: {}", e).as_bytes())?;
+                }
+            }
+            Ok(())
+        })
+        .map_err(|e| format!("Failed to register /connect handler: {:?}", e))?;
+
+    Ok(server)
+}
+
+/// Decode an `application/x-www-form-urlencoded` body for the `ssid`/
+/// `password` fields. Minimal on purpose: the captive portal only ever
+/// submits these two.
+fn parse_form_credentials(body: &str) -> (String, String) {
+    let mut ssid = String::new();
+    let mut password = String::new();
+    for pair in body.split('&') {
+        let mut parts = pair.splitn(2, '=');
+        let key = parts.next().unwrap_or("");
+        let value = url_decode(parts.next().unwrap_or(""));
+        match key {
+            "ssid" => ssid = value,
+            "password" => password = value,
+            _ => {}
+        }
+    }
+    (ssid, password)
+}
+
+/// Decode `+` and `%XX` escapes from a urlencoded form value.
+fn url_decode(value: &str) -> String {
+    // Percent-decoded bytes accumulate here rather than going straight into
+    // a String, since a multi-byte UTF-8 sequence (e.g. an accented SSID
+    // character) arrives one raw byte per "%XX" and only means something
+    // once all of its bytes are decoded together.
+    let mut out = Vec::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '+' => out.push(b' '),
+            '%' => {
+                let (hi, lo) = (chars.next(), chars.next());
+                match (hi, lo) {
+                    (Some(hi), Some(lo)) => {
+                        match u8::from_str_radix(&format!("{}{}", hi, lo), 16) {
+                            Ok(byte) => out.push(byte),
+                            Err(_) => out.push(b'%'),
+                        }
+                    }
+                    _ => out.push(b'%'),
+                }
+            }
+            other => out.extend(other.encode_utf8(&mut [0u8; 4]).as_bytes()),
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Captive-portal DNS responder: answers every query with the SoftAP's own
+/// address (192.168.4.1) so phones' captive-portal detection opens the
+/// provisioning page. Runs until `stop` is set, checking it between
+/// receives via a read timeout, the same polling shape `printer_discover`
+/// uses for its scan deadline.
+fn run_captive_dns(stop: Arc<AtomicBool>) {
+    use std::net::UdpSocket;
+
+    let socket = match UdpSocket::bind("0.0.0.0:53") {
+        Ok(s) => s,
+        Err(e) => {
+            error!("Captive portal DNS: failed to bind 0.0.0.0:53: {:?}", e);
+            return;
+        }
+    };
+
+    if let Err(e) = socket.set_read_timeout(Some(Duration::from_millis(500))) {
+        warn!("Captive portal DNS: failed to set read timeout: {:?}", e);
+    }
+
+    info!("Captive portal DNS responder listening on 0.0.0.0:53");
+
+    let mut buf = [0u8; 512];
+    while !stop.load(Ordering::Relaxed) {
+        let (len, addr) = match socket.recv_from(&mut buf) {
+            Ok(v) => v,
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock || e.kind() == std::io::ErrorKind::TimedOut => continue,
+            Err(e) => {
+                warn!("Captive portal DNS: recv failed: {:?}", e);
+                continue;
+            }
+        };
+
+        if let Some(response) = build_dns_reply(&buf[..len]) {
+            if let Err(e) = socket.send_to(&response, addr) {
+                warn!("Captive portal DNS: send failed: {:?}", e);
+            }
+        }
+    }
+
+    info!("Captive portal DNS responder stopped");
+}
+
+/// Build an A-record reply pointing at the SoftAP's own IP for any query,
+/// given the 12-byte DNS header plus question section. Returns `None` if
+/// `query` is too short to contain a complete header and question.
+fn build_dns_reply(query: &[u8]) -> Option<Vec<u8>> {
+    const AP_IP: [u8; 4] = [192, 168, 4, 1];
+
+    if query.len() < 12 {
+        return None;
+    }
+
+    // The question section starts right after the 12-byte header and runs
+    // through the null-terminated QNAME plus 2 bytes QTYPE + 2 bytes QCLASS.
+    let mut pos = 12;
+    while pos < query.len() && query[pos] != 0 {
+        pos += query[pos] as usize + 1;
+    }
+    if pos >= query.len() {
+        return None;
+    }
+    let question_end = pos + 1 + 4; // null label + QTYPE + QCLASS
+    if question_end > query.len() {
+        return None;
+    }
+    let question = &query[12..question_end];
+
+    let mut reply = Vec::with_capacity(question_end + 16);
+    reply.extend_from_slice(&query[0..2]); // ID, echoed
+    reply.extend_from_slice(&[0x81, 0x80]); // flags: standard response, recursion available
+    reply.extend_from_slice(&[0x00, 0x01]); // QDCOUNT = 1
+    reply.extend_from_slice(&[0x00, 0x01]); // ANCOUNT = 1
+    reply.extend_from_slice(&[0x00, 0x00]); // NSCOUNT = 0
+    reply.extend_from_slice(&[0x00, 0x00]); // ARCOUNT = 0
+    reply.extend_from_slice(question); // question section, echoed back
+
+    reply.extend_from_slice(&[0xC0, 0x0C]); // NAME: pointer to the question's QNAME
+    reply.extend_from_slice(&[0x00, 0x01]); // TYPE = A
+    reply.extend_from_slice(&[0x00, 0x01]); // CLASS = IN
+    reply.extend_from_slice(&[0x00, 0x00, 0x00, 0x3C]); // TTL = 60s
+    reply.extend_from_slice(&[0x00, 0x04]); // RDLENGTH = 4
+    reply.extend_from_slice(&AP_IP); // RDATA
+
+    Some(reply)
+}
+
+// ============================================================================
+// C-callable interface
+// ============================================================================
+
+/// WiFi status codes for C interface
+#[repr(C)]
+pub struct WifiStatus {
+    /// 0=Uninitialized, 1=Disconnected, 2=Connecting, 3=Connected, 4=Error, 5=AccessPoint
+    pub state: c_int,
+    /// IP address bytes (valid when state=3)
+    pub ip: [u8; 4],
+    /// Signal strength in dBm (valid when state=3), 0 if unknown
+    pub rssi: i8,
+    /// Negotiated auth mode (valid when state=3): 0=Open, 1=WEP, 2=WPA,
+    /// 3=WPA2, 4=WPA3, 5=mixed WPA2/WPA3, 6=Enterprise, 7=WAPI
+    pub auth_mode: u8,
+    /// 1 if modem power-save (min-modem or max-modem sleep) is active, 0 if
+    /// running at full power
+    pub sleep_active: u8,
+    /// BSSID of the associated AP (valid when state=3)
+    pub bssid: [u8; 6],
+    /// WiFi channel of the associated AP (valid when state=3), 0 if unknown
+    pub channel: u8,
+}
+
+/// WiFi scan result for C interface
+#[repr(C)]
+pub struct WifiScanResult {
+    /// SSID (null-terminated)
+    pub ssid: [c_char; 33],
+    /// Signal strength in dBm
+    pub rssi: i8,
+    /// Auth mode: 0=Open, 1=WEP, 2=WPA, 3=WPA2, 4=WPA3
+    pub auth_mode: u8,
+}
+
+/// Saved-network entry for C interface
+#[repr(C)]
+pub struct SavedNetworkInfo {
+    /// SSID (null-terminated)
+    pub ssid: [c_char; 33],
+    /// 1 if a password is stored for this network, 0 if open
+    pub has_password: u8,
+}
+
+/// Add (or update the password of) a saved network
+/// Returns 0 on success, -1 on error
+#[no_mangle]
+pub extern "C" fn wifi_add_network(ssid: *const c_char, password: *const c_char) -> c_int {
+    if ssid.is_null() {
+        error!("wifi_add_network: SSID is null");
+        return -1;
+    }
+
+    let ssid_str = unsafe {
+        match CStr::from_ptr(ssid).to_str() {
+            Ok(s) => s,
+            Err(_) => {
+                error!("wifi_add_network: Invalid SSID string");
+                return -1;
+            }
+        }
+    };
+
+    let password_str = if password.is_null() {
+        ""
+    } else {
+        unsafe {
+            match CStr::from_ptr(password).to_str() {
+                Ok(s) => s,
+                Err(_) => {
+                    error!("wifi_add_network: Invalid password string");
+                    return -1;
+                }
+            }
+        }
+    };
+
+    match add_network_internal(ssid_str, password_str) {
+        Ok(()) => 0,
+        Err(e) => {
+            error!("wifi_add_network failed: {}", e);
+            -1
+        }
+    }
+}
+
+/// Remove a saved network by SSID
+/// Returns 0 on success, -1 on error
+#[no_mangle]
+pub extern "C" fn wifi_remove_network(ssid: *const c_char) -> c_int {
+    if ssid.is_null() {
+        error!("wifi_remove_network: SSID is null");
+        return -1;
+    }
+
+    let ssid_str = unsafe {
+        match CStr::from_ptr(ssid).to_str() {
+            Ok(s) => s,
+            Err(_) => {
+                error!("wifi_remove_network: Invalid SSID string");
+                return -1;
+            }
+        }
+    };
+
+    match remove_network_internal(ssid_str) {
+        Ok(()) => 0,
+        Err(e) => {
+            error!("wifi_remove_network failed: {}", e);
+            -1
+        }
     }
-    -50 // Default moderate signal if we can't get it
 }
 
-/// Get current WiFi state
-fn get_state() -> WifiState {
-    let manager_guard = WIFI_MANAGER.lock().unwrap();
-    match manager_guard.as_ref() {
-        Some(manager) => manager.state.clone(),
-        None => WifiState::Uninitialized,
+/// List saved networks
+/// Fills the results array with up to max_results entries
+/// Returns the number of entries written, or -1 on error
+#[no_mangle]
+pub extern "C" fn wifi_list_networks(results: *mut SavedNetworkInfo, max_results: c_int) -> c_int {
+    if results.is_null() || max_results <= 0 {
+        return -1;
     }
-}
 
-// ============================================================================
-// C-callable interface
-// ============================================================================
+    let manager_guard = WIFI_MANAGER.lock().unwrap();
+    let Some(manager) = manager_guard.as_ref() else {
+        error!("wifi_list_networks: WiFi not initialized");
+        return -1;
+    };
 
-/// WiFi status codes for C interface
-#[repr(C)]
-pub struct WifiStatus {
-    /// 0=Uninitialized, 1=Disconnected, 2=Connecting, 3=Connected, 4=Error
-    pub state: c_int,
-    /// IP address bytes (valid when state=3)
-    pub ip: [u8; 4],
-    /// Signal strength in dBm (valid when state=3), 0 if unknown
-    pub rssi: i8,
-}
+    let count = std::cmp::min(manager.networks.len(), max_results as usize);
+    for (i, (ssid, password)) in manager.networks.iter().take(count).enumerate() {
+        unsafe {
+            let result = &mut *results.add(i);
 
-/// WiFi scan result for C interface
-#[repr(C)]
-pub struct WifiScanResult {
-    /// SSID (null-terminated)
-    pub ssid: [c_char; 33],
-    /// Signal strength in dBm
-    pub rssi: i8,
-    /// Auth mode: 0=Open, 1=WEP, 2=WPA, 3=WPA2, 4=WPA3
-    pub auth_mode: u8,
+            let ssid_bytes = ssid.as_bytes();
+            let ssid_len = std::cmp::min(ssid_bytes.len(), 32);
+            std::ptr::copy_nonoverlapping(ssid_bytes.as_ptr(), result.ssid.as_mut_ptr() as *mut u8, ssid_len);
+            result.ssid[ssid_len] = 0;
+
+            result.has_password = if password.is_empty() { 0 } else { 1 };
+        }
+    }
+
+    count as c_int
 }
 
 /// Initialize WiFi system - called from main.rs, not from C
@@ -337,6 +1410,91 @@ pub extern "C" fn wifi_connect(ssid: *const c_char, password: *const c_char) ->
     }
 }
 
+/// Connect to a WPA2-Enterprise (802.1X) network: configures EAP
+/// identity/username/password on the STA interface, then proceeds through
+/// the same non-blocking worker path as `wifi_connect`.
+/// Returns 0 if connection started, -1 on error
+#[no_mangle]
+pub extern "C" fn wifi_connect_enterprise(
+    ssid: *const c_char,
+    identity: *const c_char,
+    username: *const c_char,
+    password: *const c_char,
+) -> c_int {
+    if ssid.is_null() {
+        error!("wifi_connect_enterprise: SSID is null");
+        return -1;
+    }
+
+    let ssid_str = unsafe {
+        match CStr::from_ptr(ssid).to_str() {
+            Ok(s) => s,
+            Err(_) => {
+                error!("wifi_connect_enterprise: Invalid SSID string");
+                return -1;
+            }
+        }
+    };
+
+    if identity.is_null() {
+        error!("wifi_connect_enterprise: identity is null");
+        return -1;
+    }
+
+    let identity_str = unsafe {
+        match CStr::from_ptr(identity).to_str() {
+            Ok(s) => s,
+            Err(_) => {
+                error!("wifi_connect_enterprise: Invalid identity string");
+                return -1;
+            }
+        }
+    };
+
+    if username.is_null() {
+        error!("wifi_connect_enterprise: username is null");
+        return -1;
+    }
+
+    let username_str = unsafe {
+        match CStr::from_ptr(username).to_str() {
+            Ok(s) => s,
+            Err(_) => {
+                error!("wifi_connect_enterprise: Invalid username string");
+                return -1;
+            }
+        }
+    };
+
+    if password.is_null() {
+        error!("wifi_connect_enterprise: password is null");
+        return -1;
+    }
+
+    let password_str = unsafe {
+        match CStr::from_ptr(password).to_str() {
+            Ok(s) => s,
+            Err(_) => {
+                error!("wifi_connect_enterprise: Invalid password string");
+                return -1;
+            }
+        }
+    };
+
+    if let Err(e) = configure_enterprise_eap(identity_str, username_str, password_str) {
+        error!("wifi_connect_enterprise: {}", e);
+        return -1;
+    }
+
+    match start_connect(ssid_str, password_str) {
+        Ok(_) => 0,
+        Err(e) => {
+            error!("wifi_connect_enterprise failed: {}", e);
+            -1
+        }
+    }
+}
+
 /// Get current WiFi status
 /// Fills the provided WifiStatus struct
 #[no_mangle]
@@ -346,38 +1504,90 @@ pub extern "C" fn wifi_get_status(status: *mut WifiStatus) {
     }
 
     let state = get_state();
+    let sleep_active = if get_power_save_mode() != POWER_SAVE_NONE { 1 } else { 0 };
 
     unsafe {
+        (*status).sleep_active = sleep_active;
         match state {
             WifiState::Uninitialized => {
                 (*status).state = 0;
                 (*status).ip = [0, 0, 0, 0];
                 (*status).rssi = 0;
+                (*status).auth_mode = 0;
+                (*status).bssid = [0; 6];
+                (*status).channel = 0;
             }
             WifiState::Disconnected => {
                 (*status).state = 1;
                 (*status).ip = [0, 0, 0, 0];
                 (*status).rssi = 0;
+                (*status).auth_mode = 0;
+                (*status).bssid = [0; 6];
+                (*status).channel = 0;
             }
             WifiState::Connecting => {
                 (*status).state = 2;
                 (*status).ip = [0, 0, 0, 0];
                 (*status).rssi = 0;
+                (*status).auth_mode = 0;
+                (*status).bssid = [0; 6];
+                (*status).channel = 0;
             }
-            WifiState::Connected { ip, rssi } => {
+            WifiState::Connected { ip, rssi, auth_mode, bssid, channel } => {
                 (*status).state = 3;
                 (*status).ip = ip;
-                (*status).rssi = rssi;
+                // Prefer a live reading over the value cached at connect
+                // time; fall back to it if the driver query fails.
+                (*status).rssi = query_sta_ap_info().map(|(rssi, ..)| rssi).unwrap_or(rssi);
+                (*status).auth_mode = auth_mode;
+                (*status).bssid = bssid;
+                (*status).channel = channel;
             }
             WifiState::Error(_) => {
                 (*status).state = 4;
                 (*status).ip = [0, 0, 0, 0];
                 (*status).rssi = 0;
+                (*status).auth_mode = 0;
+                (*status).bssid = [0; 6];
+                (*status).channel = 0;
+            }
+            WifiState::AccessPoint { .. } => {
+                (*status).state = 5;
+                (*status).ip = [192, 168, 4, 1];
+                (*status).rssi = 0;
+                (*status).auth_mode = 0;
+                (*status).bssid = [0; 6];
+                (*status).channel = 0;
             }
         }
     }
 }
 
+/// Start the AP-fallback provisioning portal (SoftAP + captive HTTP/DNS).
+/// Returns 0 on success, -1 on error.
+#[no_mangle]
+pub extern "C" fn wifi_start_ap_portal() -> c_int {
+    match start_ap_portal() {
+        Ok(()) => 0,
+        Err(e) => {
+            error!("wifi_start_ap_portal failed: {}", e);
+            -1
+        }
+    }
+}
+
+/// Stop the AP-fallback provisioning portal. Returns 0 on success, -1 on error.
+#[no_mangle]
+pub extern "C" fn wifi_stop_ap_portal() -> c_int {
+    match stop_ap_portal() {
+        Ok(()) => 0,
+        Err(e) => {
+            error!("wifi_stop_ap_portal failed: {}", e);
+            -1
+        }
+    }
+}
+
 /// Disconnect from WiFi
 /// Returns 0 on success, -1 on error
 #[no_mangle]
@@ -385,6 +1595,14 @@ pub extern "C" fn wifi_disconnect() -> c_int {
     let mut manager_guard = WIFI_MANAGER.lock().unwrap();
 
     if let Some(manager) = manager_guard.as_mut() {
+        // Tell the worker to forget the current network and stop any
+        // in-flight backoff retry before disconnecting, so the
+        // `StaDisconnected` event this triggers doesn't bounce straight
+        // back into a reconnect.
+        if let Some(tx) = &manager.worker_tx {
+            let _ = tx.send(WifiWorkerCommand::ManualDisconnect);
+        }
+
         if let Some(wifi) = manager.wifi.as_mut() {
             match wifi.disconnect() {
                 Ok(_) => {
@@ -403,6 +1621,69 @@ pub extern "C" fn wifi_disconnect() -> c_int {
     -1
 }
 
+/// Enable or disable auto-reconnect (retry with exponential backoff after
+/// an unexpected disconnect). Takes effect immediately, including mid-retry.
+/// Returns 0 on success, -1 on error.
+#[no_mangle]
+pub extern "C" fn wifi_set_auto_reconnect(enable: c_int) -> c_int {
+    let manager_guard = WIFI_MANAGER.lock().unwrap();
+    let Some(manager) = manager_guard.as_ref() else {
+        error!("wifi_set_auto_reconnect: WiFi not initialized");
+        return -1;
+    };
+    let Some(tx) = manager.worker_tx.clone() else {
+        error!("wifi_set_auto_reconnect: WiFi worker not running");
+        return -1;
+    };
+    drop(manager_guard);
+
+    match tx.send(WifiWorkerCommand::SetAutoReconnect(enable != 0)) {
+        Ok(()) => 0,
+        Err(_) => -1,
+    }
+}
+
+/// Set the modem power-save mode: 0=none (full power), 1=min-modem sleep
+/// (default), 2=max-modem sleep. Persisted to NVS so it survives a reboot,
+/// and applied to the driver immediately if WiFi is already started.
+/// Returns 0 on success, -1 on error.
+#[no_mangle]
+pub extern "C" fn wifi_set_power_save(mode: c_int) -> c_int {
+    if !(0..=2).contains(&mode) {
+        error!("wifi_set_power_save: invalid mode {}", mode);
+        return -1;
+    }
+    let mode = mode as u8;
+
+    let mut manager_guard = WIFI_MANAGER.lock().unwrap();
+    let Some(manager) = manager_guard.as_mut() else {
+        error!("wifi_set_power_save: WiFi not initialized");
+        return -1;
+    };
+
+    manager.power_save = mode;
+    let nvs = manager.nvs.clone();
+
+    let applied = if let Some(wifi) = manager.wifi.as_mut() {
+        if wifi.is_started().unwrap_or(false) {
+            apply_power_save(wifi, mode)
+        } else {
+            Ok(())
+        }
+    } else {
+        Ok(())
+    };
+    drop(manager_guard);
+
+    save_power_save_mode(nvs.as_ref(), mode);
+
+    if let Err(e) = applied {
+        error!("wifi_set_power_save: {}", e);
+        return -1;
+    }
+    0
+}
+
 /// Check if WiFi is connected
 /// Returns 1 if connected, 0 otherwise
 #[no_mangle]
@@ -439,6 +1720,28 @@ pub extern "C" fn wifi_get_ssid(buf: *mut c_char, buf_len: c_int) -> c_int {
     }
 }
 
+/// Get the most recent hardware self-test report (see `diagnostics`) as JSON.
+/// Copies into `buf` like `wifi_get_ssid`. Returns the length written, or -1
+/// if the buffer is invalid or no self-test has run yet.
+#[no_mangle]
+pub extern "C" fn wifi_get_diagnostics_json(buf: *mut c_char, buf_len: c_int) -> c_int {
+    if buf.is_null() || buf_len <= 0 {
+        return -1;
+    }
+
+    let Some(report) = crate::diagnostics::last_report() else {
+        return -1;
+    };
+
+    let json = report.to_json();
+    let copy_len = std::cmp::min(json.len(), (buf_len - 1) as usize);
+    unsafe {
+        std::ptr::copy_nonoverlapping(json.as_ptr(), buf as *mut u8, copy_len);
+        *buf.add(copy_len) = 0;
+    }
+    copy_len as c_int
+}
+
 /// Scan for WiFi networks
 /// Fills the results array with up to max_results entries
 /// Returns the number of networks found, or -1 on error
@@ -535,7 +1838,11 @@ pub extern "C" fn wifi_get_rssi() -> i8 {
     match manager_guard.as_ref() {
         Some(manager) => {
             match &manager.state {
-                WifiState::Connected { rssi, .. } => *rssi,
+                // Poll the driver directly so this tracks the signal as the
+                // device or router moves, rather than the value cached at
+                // connect time; fall back to that cached value if the
+                // driver query fails.
+                WifiState::Connected { rssi, .. } => query_sta_ap_info().map(|(rssi, ..)| rssi).unwrap_or(*rssi),
                 _ => 0,
             }
         }
@@ -558,6 +1865,8 @@ pub struct PrinterDiscoveryResult {
     pub ip: [c_char; 16],
     /// Model name (null-terminated)
     pub model: [c_char; 32],
+    /// Model series (0=X1, 1=P1, 2=A1, 3=P2, 4=H2, 5=Unknown), per `series_code`
+    pub series: u8,
 }
 
 /// Discover Bambu printers on the network via UDP broadcast
@@ -659,6 +1968,8 @@ pub extern "C" fn printer_discover(results: *mut PrinterDiscoveryResult, max_res
                         let model_len = std::cmp::min(model_bytes.len(), 31);
                         std::ptr::copy_nonoverlapping(model_bytes.as_ptr(), result.model.as_mut_ptr() as *mut u8, model_len);
                         result.model[model_len] = 0;
+
+                        result.series = series_code(printer_info.4);
                     }
                     count += 1;
                     info!("Found printer: {} ({}) at {}", printer_info.0, printer_info.1, printer_info.2);
@@ -679,78 +1990,45 @@ pub extern "C" fn printer_discover(results: *mut PrinterDiscoveryResult, max_res
     count as c_int
 }
 
-/// Parse Bambu printer discovery response
-/// Returns (name, serial, ip, model) if valid
-fn parse_printer_response(data: &[u8], source_ip: &str) -> Option<(String, String, String, String)> {
-    // Extract IP from source address (remove port)
-    let ip = source_ip.split(':').next().unwrap_or(source_ip).to_string();
-
-    // Log raw bytes for debugging (first 100 bytes as hex)
-    let hex_preview: String = data.iter().take(100).map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" ");
-    info!("Raw response from {} ({} bytes): {}", ip, data.len(), hex_preview);
-
-    let text = match std::str::from_utf8(data) {
-        Ok(t) => t,
-        Err(e) => {
-            warn!("Response is not valid UTF-8: {:?}", e);
-            return None;
-        }
-    };
+/// Tolerant decode target for a Bambu discovery JSON payload. Field names
+/// vary across firmware versions and product lines, so each one accepts
+/// every alias actually seen in the wild rather than assuming one fixed
+/// key, and `serial`/`model` accept either a JSON string or number since
+/// some firmware reports a numeric serial.
+#[derive(serde::Deserialize, Default)]
+struct DiscoveryPayload {
+    #[serde(alias = "dev_sn", alias = "sn", default, deserialize_with = "string_or_number")]
+    serial: Option<String>,
+    #[serde(alias = "product_name", alias = "dev_product_name", alias = "machine_type", default, deserialize_with = "string_or_number")]
+    model: Option<String>,
+    #[serde(alias = "dev_name", alias = "machine_name", default)]
+    name: Option<String>,
+}
 
-    // Log full response for debugging
-    info!("Text response from {}: {}", ip, text);
+/// Accepts either a JSON string or number where `DiscoveryPayload` expects a
+/// string, so a numeric serial/model value decodes instead of failing.
+fn string_or_number<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::Error;
+    match Option::<serde_json::Value>::deserialize(deserializer)? {
+        None | Some(serde_json::Value::Null) => Ok(None),
+        Some(serde_json::Value::String(s)) => Ok(Some(s)),
+        Some(serde_json::Value::Number(n)) => Ok(Some(n.to_string())),
+        Some(other) => Err(D::Error::custom(format!("expected string or number, got {other}"))),
+    }
+}
 
+/// Parse SSDP/HTTP discovery headers: "HeaderName: value" or
+/// "HeaderName.bambu.com: value", one per line. Used as a fallback when a
+/// discovery response isn't JSON.
+/// Returns (serial, model, name), each empty if not found.
+fn parse_printer_response_headers(text: &str) -> (String, String, String) {
     let mut serial = String::new();
     let mut model = String::new();
     let mut name = String::new();
 
-    // Bambu printers respond with JSON containing printer info
-    // Common fields: "dev_sn", "sn", "name", "product_name", "dev_name", "machine_name"
-
-    // Try multiple field names for serial
-    for key in &["\"dev_sn\"", "\"sn\"", "\"serial\""] {
-        if serial.is_empty() {
-            if let Some(pos) = text.find(key) {
-                if let Some(value) = extract_json_string_value(&text[pos..]) {
-                    if !value.is_empty() {
-                        serial = value;
-                        info!("Found serial from {}: {}", key, serial);
-                    }
-                }
-            }
-        }
-    }
-
-    // Try multiple field names for model/product
-    for key in &["\"product_name\"", "\"model\"", "\"dev_product_name\"", "\"machine_type\""] {
-        if model.is_empty() {
-            if let Some(pos) = text.find(key) {
-                if let Some(value) = extract_json_string_value(&text[pos..]) {
-                    if !value.is_empty() {
-                        model = value;
-                        info!("Found model from {}: {}", key, model);
-                    }
-                }
-            }
-        }
-    }
-
-    // Try multiple field names for printer name
-    for key in &["\"dev_name\"", "\"machine_name\"", "\"name\""] {
-        if name.is_empty() {
-            if let Some(pos) = text.find(key) {
-                if let Some(value) = extract_json_string_value(&text[pos..]) {
-                    if !value.is_empty() {
-                        name = value;
-                        info!("Found name from {}: {}", key, name);
-                    }
-                }
-            }
-        }
-    }
-
-    // Parse SSDP/HTTP headers from Bambu printers
-    // Format: "HeaderName: value" or "HeaderName.bambu.com: value"
     for line in text.lines() {
         let line = line.trim();
 
@@ -801,6 +2079,65 @@ fn parse_printer_response(data: &[u8], source_ip: &str) -> Option<(String, Strin
         }
     }
 
+    (serial, model, name)
+}
+
+/// Parse Bambu printer discovery response
+/// Returns (name, serial, ip, model, series) if valid
+fn parse_printer_response(data: &[u8], source_ip: &str) -> Option<(String, String, String, String, bambu_models::ModelSeries)> {
+    // Extract IP from source address (remove port)
+    let ip = source_ip.split(':').next().unwrap_or(source_ip).to_string();
+
+    // Log raw bytes for debugging (first 100 bytes as hex)
+    let hex_preview: String = data.iter().take(100).map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" ");
+    info!("Raw response from {} ({} bytes): {}", ip, data.len(), hex_preview);
+
+    let text = match std::str::from_utf8(data) {
+        Ok(t) => t,
+        Err(e) => {
+            warn!("Response is not valid UTF-8: {:?}", e);
+            return None;
+        }
+    };
+
+    // Log full response for debugging
+    info!("Text response from {}: {}", ip, text);
+
+    // Bambu printers mostly respond with JSON, but the exact shape (and even
+    // whether fields nest differently) has drifted across firmware versions,
+    // so decode it properly with serde instead of scanning for keys by hand;
+    // fall back to the line-based SSDP/`*.bambu.com` header parser when
+    // the payload isn't JSON at all (plain SSDP M-SEARCH replies).
+    let (mut serial, mut model, mut name) = match serde_json::from_str::<DiscoveryPayload>(text) {
+        Ok(payload) => {
+            info!("Decoded discovery payload as JSON");
+            (
+                payload.serial.unwrap_or_default(),
+                payload.model.unwrap_or_default(),
+                payload.name.unwrap_or_default(),
+            )
+        }
+        Err(e) => {
+            info!("Discovery payload is not JSON ({}), falling back to header parsing", e);
+            parse_printer_response_headers(text)
+        }
+    };
+
+    // The JSON decode path above doesn't see SSDP/HTTP headers (e.g. `USN:`),
+    // so fill in anything it missed from them too.
+    if serial.is_empty() || model.is_empty() || name.is_empty() {
+        let (header_serial, header_model, header_name) = parse_printer_response_headers(text);
+        if serial.is_empty() {
+            serial = header_serial;
+        }
+        if model.is_empty() {
+            model = header_model;
+        }
+        if name.is_empty() {
+            name = header_name;
+        }
+    }
+
     // Generate default name if not found
     if name.is_empty() {
         if !serial.is_empty() && !model.is_empty() {
@@ -821,66 +2158,26 @@ fn parse_printer_response(data: &[u8], source_ip: &str) -> Option<(String, Strin
         }
     }
 
-    // Map Bambu model codes to friendly names
-    // Reference: https://github.com/bambulab/BambuStudio/tree/master/resources/printers
-    let friendly_model = match model.as_str() {
-        // X1 Series
-        "BL-P001" => "X1 Carbon",
-        "BL-P002" => "X1",
-        "C13" => "X1E",
-        // P1 Series
-        "C11" => "P1P",
-        "C12" => "P1S",
-        // A1 Series
-        "N1" => "A1 Mini",
-        "N2S" => "A1",
-        // P2 Series
-        "N7" => "P2S",
-        // H2 Series
-        "O1C" | "O1C2" => "H2C",
-        "O1D" => "H2D",
-        "O1E" => "H2D Pro",
-        "O1S" => "H2S",
-        "" => "Bambu Printer",
-        other => other, // Keep unknown codes as-is
-    };
-    let model = friendly_model.to_string();
-
-    info!("Final parsed: name='{}', serial='{}', model='{}', ip='{}'", name, serial, model, ip);
-    Some((name, serial, ip, model))
-}
-
-/// Extract a JSON string value from text starting at a key
-/// Input: "\"key\": \"value\"..." or "\"key\":\"value\"..."
-/// Returns: Some("value") or None
-fn extract_json_string_value(text: &str) -> Option<String> {
-    // Find the colon after the key
-    let colon_pos = text.find(':')?;
-    let after_colon = &text[colon_pos + 1..];
-
-    // Find the opening quote
-    let quote_start = after_colon.find('"')?;
-    let value_start = quote_start + 1;
-    let remaining = &after_colon[value_start..];
-
-    // Find the closing quote (handle escaped quotes)
-    let mut end_pos = 0;
-    let mut chars = remaining.chars().peekable();
-    while let Some(c) = chars.next() {
-        if c == '\\' {
-            // Skip escaped character
-            chars.next();
-            end_pos += 2;
-        } else if c == '"' {
-            break;
-        } else {
-            end_pos += c.len_utf8();
-        }
-    }
+    // Map the raw discovery model code to a friendly name and series via the
+    // table generated from resources/bambu_models.csv (see build.rs), so
+    // adding a newly released printer is a data-file edit, not a source change.
+    let series = bambu_models::series(&model);
+    let model = bambu_models::friendly_name(&model);
 
-    if end_pos > 0 || remaining.starts_with('"') {
-        Some(remaining[..end_pos].to_string())
-    } else {
-        None
+    info!("Final parsed: name='{}', serial='{}', model='{}', series={:?}, ip='{}'", name, serial, model, series, ip);
+    Some((name, serial, ip, model, series))
+}
+
+/// Maps a `bambu_models::ModelSeries` to a 0-5 numeric code for the C
+/// interface, the same scheme `auth_mode_code` uses for `AuthMethod`.
+fn series_code(series: bambu_models::ModelSeries) -> u8 {
+    match series {
+        bambu_models::ModelSeries::X1 => 0,
+        bambu_models::ModelSeries::P1 => 1,
+        bambu_models::ModelSeries::A1 => 2,
+        bambu_models::ModelSeries::P2 => 3,
+        bambu_models::ModelSeries::H2 => 4,
+        bambu_models::ModelSeries::Unknown => 5,
     }
 }
+